@@ -0,0 +1,91 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*, SeekFrom};
+
+/// Internal wrapper which turns every read into a read of whole blocks, for block devices
+/// which reject reads that don't start and end on a block boundary
+pub(crate) struct AlignedReader<F> {
+    input: F,
+    block_size: u64,
+    position: u64,
+    len: Option<u64>,
+    block_buffer: Vec<u8>,
+}
+
+impl<F> AlignedReader<F> {
+    pub(crate) fn new(input: F, block_size: u64) -> Self {
+        AlignedReader {
+            input,
+            block_size,
+            position: 0,
+            len: None,
+            block_buffer: vec![],
+        }
+    }
+}
+
+impl<F: Read + Seek> Read for AlignedReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let aligned_start = self.position / self.block_size * self.block_size;
+        let skip = (self.position - aligned_start) as usize;
+        let aligned_len =
+            (skip + buf.len()).div_ceil(self.block_size as usize) * self.block_size as usize;
+
+        self.block_buffer.resize(aligned_len, 0);
+        self.input.seek(SeekFrom::Start(aligned_start))?;
+        let bytes_read = self.input.read(&mut self.block_buffer[..])?;
+
+        if bytes_read <= skip {
+            return Ok(0);
+        }
+
+        let available = (bytes_read - skip).min(buf.len());
+        buf[..available].copy_from_slice(&self.block_buffer[skip..skip + available]);
+        self.position += available as u64;
+
+        Ok(available)
+    }
+}
+
+impl<F: Read + Seek> Seek for AlignedReader<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                let len = match self.len {
+                    Some(len) => len,
+                    None => *self.len.insert(self.input.seek(SeekFrom::End(0))?),
+                };
+                len.checked_add_signed(offset)
+            }
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
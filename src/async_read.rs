@@ -0,0 +1,334 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, SeekFrom};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    checksum::ChecksumMode,
+    framing::{
+        check_magic, decode_varint, magic_and_length_location, offset_pointer_location,
+        unsupported_framing, Framing, LengthEncoding, FOOTER_LEN, VARINT_MAX_LEN,
+    },
+    scan::{outermost_location, Scan},
+    CatConfError, ConfReaderOptions, PayloadLocation, ScanDirection,
+};
+
+impl ConfReaderOptions {
+    /// Async counterpart to reading the configuration, for use with tokio. Locates the payload
+    /// and returns a reader which is positioned at the start of the payload and stops at the
+    /// end of it, so that it can be piped into async decoders without being buffered
+    ///
+    /// The magic bytes, window size, magic alignment, framing, and length encoding are
+    /// honored. A checksum, match index, forward scan, or expected length range
+    /// can't be, so setting any of them gives an [`io::ErrorKind::Unsupported`] error rather
+    /// than a reader which could return the wrong bytes. The remaining options only apply to
+    /// the synchronous readers
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let input = std::io::Cursor::new(buff);
+    /// let mut reader = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_reader_async(input)
+    ///     .await?;
+    ///
+    /// let mut conf = vec![];
+    /// reader.read_to_end(&mut conf).await?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_reader_async<F>(&self, mut input: F) -> io::Result<impl AsyncRead + Unpin>
+    where
+        F: AsyncRead + AsyncSeek + Unpin,
+    {
+        if self.checksum_opt != ChecksumMode::Absent
+            || self.match_index_opt != 0
+            || self.scan_direction_opt != ScanDirection::Backward
+            || self.length_range_opt.is_some()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "checksums, match indexes, forward scans, and length ranges aren't supported \
+                 by the async reader",
+            ));
+        }
+
+        let location = match self.framing_opt {
+            Framing::Suffix => locate_payload_async(&self.scan(), &mut input).await?,
+            Framing::OffsetPointer => {
+                locate_offset_pointer_async(&self.magic_bytes_opt, &mut input).await?
+            }
+            Framing::MagicAndLength => {
+                locate_magic_and_length_async(
+                    &self.magic_bytes_opt,
+                    self.length_encoding_opt,
+                    &mut input,
+                )
+                .await?
+            }
+            framing => return Err(unsupported_framing(framing)),
+        };
+
+        input.seek(SeekFrom::Start(location.offset)).await?;
+
+        Ok(input.take(location.len))
+    }
+}
+
+/// Async version of [`locate_payload`](crate::scan::locate_payload), for the outermost payload
+async fn locate_payload_async<F>(scan: &Scan<'_>, input: &mut F) -> io::Result<PayloadLocation>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut current_window_index: i64 = 1;
+    let mut current_read_buffer = vec![0u8; scan.window_size as usize * 2];
+    let file_len = input.seek(SeekFrom::End(0)).await? as i64;
+
+    loop {
+        let window_start = scan.outermost_window_start(file_len, current_window_index);
+        input.seek(SeekFrom::Start(window_start as u64)).await?;
+        let mut bytes_read = 0;
+        // A single read can return fewer bytes than asked for before the end of the input
+        while bytes_read < current_read_buffer.len() {
+            match input.read(&mut current_read_buffer[bytes_read..]).await? {
+                0 => break,
+                len => bytes_read += len,
+            }
+        }
+
+        if let Some(location) = outermost_location(
+            scan,
+            &current_read_buffer[..bytes_read],
+            window_start as u64,
+            file_len as u64,
+        ) {
+            break Ok(location);
+        }
+
+        if window_start == 0 {
+            break Err(CatConfError::NotFound.into());
+        }
+
+        current_window_index += 1;
+    }
+}
+
+/// Async version of [`locate_offset_pointer`](crate::framing::locate_offset_pointer)
+async fn locate_offset_pointer_async<F>(
+    magic_bytes: &[u8],
+    input: &mut F,
+) -> io::Result<PayloadLocation>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+{
+    let footer_start = input.seek(SeekFrom::End(-(FOOTER_LEN as i64))).await?;
+    let magic_offset = input.read_u64_le().await?;
+    let location = offset_pointer_location(magic_bytes.len() as u64, magic_offset, footer_start)?;
+
+    input.seek(SeekFrom::Start(magic_offset)).await?;
+    verify_magic_async(
+        magic_bytes,
+        input,
+        "magic bytes not found at the offset pointer",
+    )
+    .await?;
+
+    Ok(location)
+}
+
+/// Async version of [`locate_magic_and_length`](crate::framing::locate_magic_and_length)
+async fn locate_magic_and_length_async<F>(
+    magic_bytes: &[u8],
+    encoding: LengthEncoding,
+    input: &mut F,
+) -> io::Result<PayloadLocation>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+{
+    let (len, footer_start) = match encoding {
+        LengthEncoding::U64 => {
+            let footer_start = input.seek(SeekFrom::End(-(FOOTER_LEN as i64))).await?;
+            (input.read_u64_le().await?, footer_start)
+        }
+        LengthEncoding::Varint => {
+            let file_len = input.seek(SeekFrom::End(0)).await?;
+            let tail_len = VARINT_MAX_LEN.min(file_len);
+            let mut tail = vec![0; tail_len as usize];
+            input.seek(SeekFrom::Start(file_len - tail_len)).await?;
+            input.read_exact(&mut tail).await?;
+
+            let (len, footer_len) = decode_varint(&tail)?;
+            (len, file_len - footer_len)
+        }
+    };
+    let location = magic_and_length_location(magic_bytes.len() as u64, len, footer_start)?;
+
+    input.seek(SeekFrom::Start(location.magic_offset)).await?;
+    verify_magic_async(
+        magic_bytes,
+        input,
+        "magic bytes not found before the payload length given",
+    )
+    .await?;
+
+    Ok(location)
+}
+
+/// Async version of [`verify_magic`](crate::framing::verify_magic)
+async fn verify_magic_async<F>(
+    magic_bytes: &[u8],
+    input: &mut F,
+    message: &'static str,
+) -> io::Result<()>
+where
+    F: AsyncRead + Unpin,
+{
+    let mut buffer = vec![0; magic_bytes.len()];
+    input.read_exact(&mut buffer).await?;
+
+    check_magic(magic_bytes, &buffer, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Reading through the async reader should give the same bytes as the sync reader
+    #[tokio::test]
+    async fn reads_payload_async() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..50).map(|i| (i % 7) as u8 + 10));
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let expected = options.read(&mut Cursor::new(&input_data)).unwrap();
+
+        let mut reader = options
+            .read_reader_async(Cursor::new(&input_data))
+            .await
+            .unwrap();
+        let mut conf = vec![];
+        reader.read_to_end(&mut conf).await.unwrap();
+
+        assert_eq!(conf, expected);
+    }
+
+    /// With two blocks in the same window, the async reader should give the outermost block
+    /// alone, the same as the sync reader
+    #[tokio::test]
+    async fn reads_outermost_block_async() {
+        let mut input_data = vec![0; 4096];
+        input_data.extend(b"CATCONFbase");
+        input_data.extend(b"CATCONFoverride");
+        let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+
+        let mut reader = options
+            .read_reader_async(Cursor::new(&input_data))
+            .await
+            .unwrap();
+        let mut conf = vec![];
+        reader.read_to_end(&mut conf).await.unwrap();
+
+        assert_eq!(conf, options.read(&mut Cursor::new(&input_data)).unwrap());
+        assert_eq!(conf, b"override");
+    }
+
+    /// Options which would change which bytes are returned, but which the async reader can't
+    /// honor, should be rejected instead of ignored
+    #[tokio::test]
+    async fn rejects_unsupported_options_async() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([9; 50]);
+        input_data.extend(crate::checksum::crc32(&[9; 50]).to_le_bytes());
+
+        let mut checksum = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        checksum.checksum(true);
+        let mut match_index = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        match_index.match_index(1);
+        let mut forward = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        forward.scan_direction(ScanDirection::Forward);
+        let mut length_range = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        length_range.expected_length_range(0..100);
+
+        for options in [checksum, match_index, forward, length_range] {
+            let result = options.read_reader_async(Cursor::new(&input_data)).await;
+            assert_eq!(result.err().unwrap().kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    /// The length footer should be followed back to the magic bytes in the async reader as
+    /// well, with either encoding
+    #[tokio::test]
+    async fn reads_magic_and_length_async() {
+        for encoding in [LengthEncoding::U64, LengthEncoding::Varint] {
+            let mut input_data = vec![0; 100];
+            input_data.extend([1, 2, 3, 4]);
+            input_data.extend([9; 50]);
+            input_data.extend(encoding.encode(50));
+            let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+            options.magic_and_length(true).length_encoding(encoding);
+
+            let mut reader = options
+                .read_reader_async(Cursor::new(&input_data))
+                .await
+                .unwrap();
+            let mut conf = vec![];
+            reader.read_to_end(&mut conf).await.unwrap();
+
+            assert_eq!(conf, [9; 50]);
+        }
+    }
+
+    /// The offset pointer footer should be followed in the async reader as well
+    #[tokio::test]
+    async fn reads_offset_pointer_async() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([9; 50]);
+        input_data.extend(100u64.to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.offset_pointer_footer(true);
+
+        let mut reader = options
+            .read_reader_async(Cursor::new(&input_data))
+            .await
+            .unwrap();
+        let mut conf = vec![];
+        reader.read_to_end(&mut conf).await.unwrap();
+
+        assert_eq!(conf, [9; 50]);
+        drop(reader);
+
+        let end = input_data.len() - 8;
+        input_data[end..].copy_from_slice(&[0xff; 8]);
+        let result = options.read_reader_async(Cursor::new(&input_data)).await;
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,211 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Command line tool for working with configuration appended to binaries
+//!
+//! ```text
+//! catconf extract [--magic MAGIC] (--stdout | --output PATH) FILE
+//! ```
+//!
+//! With `--stdout` the raw payload is written straight to the stdout handle, which is never
+//! translated, so it can be piped into other tools: `catconf extract --stdout app | gunzip`
+
+use std::{
+    env,
+    fs::File,
+    io::{self, prelude::*},
+    process::ExitCode,
+};
+
+use catconf::{ConfReaderOptions, DEFAULT_MAGIC};
+
+const USAGE: &str = "usage: catconf extract [--magic MAGIC] (--stdout | --output PATH) FILE";
+
+/// Where the extracted payload should be written
+#[derive(Debug, PartialEq, Eq)]
+enum Destination {
+    Stdout,
+    Path(String),
+}
+
+/// Arguments to the `extract` subcommand
+#[derive(Debug, PartialEq, Eq)]
+struct ExtractArgs {
+    magic: Vec<u8>,
+    destination: Destination,
+    input: String,
+}
+
+/// Parses the arguments following the program name, returning a message for the user if they
+/// aren't valid
+fn parse_args<I>(args: I) -> Result<ExtractArgs, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        Some("extract") => {}
+        Some(command) => return Err(format!("unknown command '{command}'\n{USAGE}")),
+        None => return Err(USAGE.to_string()),
+    }
+
+    let mut magic = DEFAULT_MAGIC.to_vec();
+    let mut destination = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--magic" => {
+                magic = args
+                    .next()
+                    .ok_or_else(|| format!("--magic requires a value\n{USAGE}"))?
+                    .into_bytes();
+            }
+            "--stdout" => destination = Some(Destination::Stdout),
+            "--output" | "-o" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| format!("--output requires a path\n{USAGE}"))?;
+                destination = Some(Destination::Path(path));
+            }
+            _ if input.is_none() && !arg.starts_with('-') => input = Some(arg),
+            _ => return Err(format!("unexpected argument '{arg}'\n{USAGE}")),
+        }
+    }
+
+    Ok(ExtractArgs {
+        magic,
+        destination: destination
+            .ok_or_else(|| format!("one of --stdout or --output is required\n{USAGE}"))?,
+        input: input.ok_or_else(|| format!("no input file given\n{USAGE}"))?,
+    })
+}
+
+/// Copies the payload from the input into the output as raw bytes. A reader which goes away
+/// before the whole payload is written, such as `head` at the end of a pipe, is not treated as
+/// an error
+fn extract<F, W>(options: &ConfReaderOptions, input: &mut F, output: &mut W) -> io::Result<()>
+where
+    F: Read + Seek,
+    W: Write,
+{
+    match options.extract_to(input, output) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let options = ConfReaderOptions::new(args.magic);
+    let result = File::open(&args.input).and_then(|mut input| match &args.destination {
+        // Rust never does newline translation on stdout, even on Windows, so the payload is
+        // written out byte for byte
+        Destination::Stdout => extract(&options, &mut input, &mut io::stdout().lock()),
+        Destination::Path(path) => extract(&options, &mut input, &mut File::create(path)?),
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("catconf: {}: {e}", args.input);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Writer which stops accepting bytes partway through, like a closed pipe
+    struct ClosedPipe {
+        accepted: usize,
+    }
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.accepted > 0 {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+
+            self.accepted += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn confed(payload: &[u8]) -> Cursor<Vec<u8>> {
+        let mut input_data = vec![0; 4096];
+        input_data.extend(DEFAULT_MAGIC);
+        input_data.extend(payload);
+        Cursor::new(input_data)
+    }
+
+    /// Every byte value, including newlines and carriage returns, should come out unchanged
+    #[test]
+    fn extracts_every_byte() {
+        let payload = (0..20_000).map(|i| (i % 256) as u8).collect::<Vec<u8>>();
+        let options = ConfReaderOptions::with_default_magic();
+
+        let mut output = vec![];
+        extract(&options, &mut confed(&payload), &mut output).unwrap();
+
+        assert_eq!(output, payload);
+    }
+
+    /// The reader closing the pipe early should not be reported as a failure
+    #[test]
+    fn ignores_broken_pipe() {
+        let payload = vec![b'x'; 20_000];
+        let options = ConfReaderOptions::with_default_magic();
+
+        let mut output = ClosedPipe { accepted: 0 };
+        extract(&options, &mut confed(&payload), &mut output).unwrap();
+
+        assert!(output.accepted < payload.len());
+    }
+
+    /// The magic bytes, destination, and input should be picked out of the arguments, and
+    /// leaving out the destination should be an error
+    #[test]
+    fn parses_extract_args() {
+        let args = ["extract", "--magic", "MYMAGIC", "--stdout", "app"].map(String::from);
+
+        assert_eq!(
+            parse_args(args),
+            Ok(ExtractArgs {
+                magic: b"MYMAGIC".to_vec(),
+                destination: Destination::Stdout,
+                input: "app".to_string(),
+            })
+        );
+        assert!(parse_args(["extract", "app"].map(String::from)).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::{self, prelude::*, SeekFrom},
+    path::Path,
+};
+
+/// Presents several files as one logical file, one after the other, so that configuration
+/// appended to a binary which has been split into parts (`binary.part1`, `binary.part2`, ...)
+/// can be read without joining the parts on disk first. The magic bytes and the configuration
+/// may straddle the boundary between two parts
+///
+/// # Example
+///
+/// ```no_run
+/// use catconf::{ConcatReadSeek, ConfReaderOptions};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut input = ConcatReadSeek::open(["binary.part1", "binary.part2"])?;
+/// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read(&mut input)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ConcatReadSeek<F> {
+    parts: Vec<F>,
+    /// Offset in the logical file at which each part starts, followed by the total length
+    starts: Vec<u64>,
+    position: u64,
+}
+
+impl ConcatReadSeek<File> {
+    /// Opens each of the paths for reading, in order
+    pub fn open<I, P>(paths: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let parts = paths
+            .into_iter()
+            .map(File::open)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Self::new(parts)
+    }
+}
+
+impl<F: Seek> ConcatReadSeek<F> {
+    /// Joins the parts in the order given. Each part is seeked to its end to find its length,
+    /// so the parts should not change size while they are being read
+    pub fn new(mut parts: Vec<F>) -> io::Result<Self> {
+        let mut starts = Vec::with_capacity(parts.len() + 1);
+        let mut total = 0u64;
+
+        for part in &mut parts {
+            starts.push(total);
+            total += part.seek(SeekFrom::End(0))?;
+        }
+        starts.push(total);
+
+        Ok(ConcatReadSeek {
+            parts,
+            starts,
+            position: 0,
+        })
+    }
+
+    /// Total length of all the parts together
+    pub fn len(&self) -> u64 {
+        self.starts[self.parts.len()]
+    }
+
+    /// Whether all of the parts are empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gives back the wrapped parts
+    pub fn into_inner(self) -> Vec<F> {
+        self.parts
+    }
+}
+
+impl<F: Read + Seek> Read for ConcatReadSeek<F> {
+    /// Keeps reading into the following parts until the buffer is full or the last part ends,
+    /// so that a read across the boundary between parts returns as much as it would from a
+    /// single file
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+
+        while filled < buf.len() && self.position < self.len() {
+            // The last part starting at or before the position; empty parts are skipped over,
+            // as they start at the same offset as the part after them
+            let index =
+                self.starts[1..self.parts.len()].partition_point(|&start| start <= self.position);
+            let offset = self.position - self.starts[index];
+            let max = (buf.len() - filled).min((self.starts[index + 1] - self.position) as usize);
+
+            let part = &mut self.parts[index];
+            part.seek(SeekFrom::Start(offset))?;
+            let bytes_read = part.read(&mut buf[filled..filled + max])?;
+
+            if bytes_read == 0 {
+                break;
+            }
+
+            filled += bytes_read;
+            self.position += bytes_read as u64;
+        }
+
+        Ok(filled)
+    }
+}
+
+impl<F: Seek> Seek for ConcatReadSeek<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(delta) => (self.len(), delta),
+            SeekFrom::Current(delta) => (self.position, delta),
+        };
+
+        self.position = base.checked_add_signed(delta).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ConfReaderOptions;
+
+    use super::*;
+
+    /// Configuration split across two parts, with the split in the middle of the magic bytes,
+    /// should read the same as the joined file
+    #[test]
+    fn reads_config_straddling_parts() {
+        let mut input_data = (0..100).map(|i| (i % 200) as u8 + 5).collect::<Vec<u8>>();
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..40).map(|i| (i % 7) as u8 + 10));
+
+        let (first, second) = input_data.split_at(102);
+        let mut input = ConcatReadSeek::new(vec![
+            Cursor::new(first.to_vec()),
+            Cursor::new(vec![]),
+            Cursor::new(second.to_vec()),
+        ])
+        .unwrap();
+
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        assert_eq!(input.len(), input_data.len() as u64);
+        assert_eq!(options.read(&mut input).unwrap(), input_data[104..]);
+
+        let mut joined = vec![];
+        input.seek(SeekFrom::Start(0)).unwrap();
+        input.read_to_end(&mut joined).unwrap();
+        assert_eq!(joined, input_data);
+    }
+}
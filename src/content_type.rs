@@ -0,0 +1,170 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    io::{self, prelude::*},
+};
+
+use crate::ConfReaderOptions;
+
+/// Content type recorded in front of the configuration by
+/// [`ConfWriterOptions::content_type`](crate::ConfWriterOptions::content_type), such as `json`
+/// or `gzip+json`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentType(String);
+
+impl ContentType {
+    /// The content type as it was recorded
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Internal function used to build the header recording the content type, which is the length
+/// of the content type as a single byte followed by the content type itself
+pub(crate) fn content_type_header(content_type: &str) -> io::Result<Vec<u8>> {
+    let len = u8::try_from(content_type.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "content type must be at most 255 bytes long",
+        )
+    })?;
+
+    let mut header = vec![len];
+    header.extend(content_type.as_bytes());
+    Ok(header)
+}
+
+impl ConfReaderOptions {
+    /// Reads configuration which was written with a content type, returning the content type
+    /// along with the configuration after it
+    ///
+    /// With the `gzip` feature, configuration with a content type starting with `gzip+` is
+    /// decompressed before being returned; the content type is returned as it was recorded
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// # ConfWriterOptions::new(b"CATCONF".to_vec())
+    /// #     .content_type("json")
+    /// #     .write(&mut file, br#"{"port": 8080}"#)?;
+    /// let (content_type, conf) = ConfReaderOptions::new(b"CATCONF".to_vec()).read_auto(&mut file)?;
+    ///
+    /// match content_type.as_str() {
+    ///     "json" => { /* parse with serde_json */ }
+    ///     _ => { /* use defaults */ }
+    /// }
+    /// # assert_eq!(conf, br#"{"port": 8080}"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_auto<F>(&self, input: &mut F) -> io::Result<(ContentType, Vec<u8>)>
+    where
+        F: Seek + Read,
+    {
+        let payload = self.read(input)?;
+
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "configuration does not start with a valid content type",
+            )
+        };
+
+        let (&len, rest) = payload.split_first().ok_or_else(invalid)?;
+        let len = len as usize;
+
+        if rest.len() < len {
+            return Err(invalid());
+        }
+
+        let content_type = std::str::from_utf8(&rest[..len]).map_err(|_| invalid())?;
+        let content_type = ContentType(content_type.to_owned());
+        let conf = &rest[len..];
+
+        #[cfg(feature = "gzip")]
+        if content_type.as_str().starts_with("gzip+") {
+            let mut decompressed = vec![];
+            flate2::read::GzDecoder::new(conf).read_to_end(&mut decompressed)?;
+            return Ok((content_type, decompressed));
+        }
+
+        Ok((content_type, conf.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ConfWriterOptions;
+
+    use super::*;
+
+    /// The content type should come back alongside the configuration
+    #[test]
+    fn round_trips_content_type() {
+        let mut file = Cursor::new(vec![0; 100]);
+
+        ConfWriterOptions::new(b"CATCONF".to_vec())
+            .content_type("toml")
+            .write(&mut file, b"port = 8080")
+            .unwrap();
+
+        let (content_type, conf) = ConfReaderOptions::new(b"CATCONF".to_vec())
+            .read_auto(&mut file)
+            .unwrap();
+
+        assert_eq!(content_type.as_str(), "toml");
+        assert_eq!(conf, b"port = 8080");
+    }
+
+    /// Configuration tagged as gzip should be decompressed automatically
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompresses_gzip_content_type() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let json = br#"{"port": 8080, "host": "localhost"}"#;
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(json).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut file = Cursor::new(vec![0; 100]);
+        ConfWriterOptions::new(b"CATCONF".to_vec())
+            .content_type("gzip+json")
+            .write(&mut file, &compressed)
+            .unwrap();
+
+        let (content_type, conf) = ConfReaderOptions::new(b"CATCONF".to_vec())
+            .read_auto(&mut file)
+            .unwrap();
+
+        assert_eq!(content_type.as_str(), "gzip+json");
+        assert_eq!(conf, json);
+    }
+}
@@ -0,0 +1,243 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*};
+
+use crate::{framing, scan::locate_payload, sections::read_index, ConfReaderOptions, Framing};
+
+/// Internal function used to turn the result of trying to locate the configuration with one
+/// of the framings into whether that framing matched. Errors from the file not matching the
+/// framing mean it isn't the right one, while any other error is passed on
+fn framing_matches<T>(result: io::Result<T>) -> io::Result<bool> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::InvalidData
+                    | io::ErrorKind::InvalidInput
+                    | io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+impl ConfReaderOptions {
+    /// Guesses which framing the configuration was written with, by checking whether the
+    /// footer at the end of the file points to the magic bytes for each of the framings with a
+    /// footer, and otherwise scanning for the magic bytes. Returns [`Framing::Unknown`] when
+    /// the magic bytes can't be found at all
+    ///
+    /// The footers are checked using the configured magic bytes and
+    /// [`ConfReaderOptions::length_encoding`], but the configured framing is ignored. As the
+    /// footer of one framing could happen to point to the magic bytes when read as another,
+    /// this is a guess meant for reading files from a mix of writers, rather than a
+    /// replacement for knowing the framing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions, Framing};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let framing = ConfReaderOptions::new(b"CATCONF".to_vec()).detect_framing(&mut file)?;
+    /// assert_eq!(framing, Framing::MagicAndLength);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_framing<F>(&self, input: &mut F) -> io::Result<Framing>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            let magic_bytes = &self.magic_bytes_opt;
+
+            if framing_matches(framing::locate_offset_pointer(magic_bytes, input))? {
+                return Ok(Framing::OffsetPointer);
+            }
+
+            if framing_matches(framing::locate_magic_and_length(
+                magic_bytes,
+                self.length_encoding_opt,
+                input,
+            ))? {
+                return Ok(Framing::MagicAndLength);
+            }
+
+            if framing_matches(read_index(magic_bytes, input))? {
+                return Ok(Framing::Sections);
+            }
+
+            if framing_matches(locate_payload(&self.scan(), input))? {
+                return Ok(Framing::Suffix);
+            }
+
+            Ok(Framing::Unknown)
+        })
+    }
+
+    /// Reads the configuration the same as [`ConfReaderOptions::read`], but using the framing
+    /// found by [`ConfReaderOptions::detect_framing`] instead of the configured one. Files
+    /// with [`Framing::Sections`] should be read with
+    /// [`ConfReaderOptions::read_section`] instead, and give an error here
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .offset_pointer_footer(true)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_auto_framing(&mut file)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_auto_framing<F>(&self, input: &mut F) -> io::Result<Vec<u8>>
+    where
+        F: Seek + Read,
+    {
+        let framing = match self.detect_framing(input)? {
+            Framing::Unknown => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "could not find the magic bytes with any framing",
+                ))
+            }
+            framing => framing,
+        };
+
+        let mut options = self.clone();
+        options.framing_opt = framing;
+        options.read(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ConfWriterOptions;
+
+    use super::*;
+
+    fn binary() -> Vec<u8> {
+        (0..500).map(|i| (i % 200) as u8 + 5).collect()
+    }
+
+    /// Each of the framings written by [`ConfWriterOptions`] should be detected and read back
+    #[test]
+    fn detects_framings() {
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        let conf = b"port=8080".as_slice();
+
+        let suffix = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        let mut offset_pointer = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        offset_pointer.offset_pointer_footer(true);
+        let mut magic_and_length = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        magic_and_length.magic_and_length(true);
+
+        for (writer, expected) in [
+            (suffix, Framing::Suffix),
+            (offset_pointer, Framing::OffsetPointer),
+            (magic_and_length, Framing::MagicAndLength),
+        ] {
+            let mut file = Cursor::new(binary());
+            file.seek(io::SeekFrom::End(0)).unwrap();
+            writer.write(&mut file, conf).unwrap();
+
+            assert_eq!(options.detect_framing(&mut file).unwrap(), expected);
+            assert_eq!(options.read_auto_framing(&mut file).unwrap(), conf);
+        }
+
+        let mut file = Cursor::new(binary());
+        file.seek(io::SeekFrom::End(0)).unwrap();
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .write_sections(&mut file, &[("app", conf)])
+            .unwrap();
+
+        assert_eq!(
+            options.detect_framing(&mut file).unwrap(),
+            Framing::Sections
+        );
+        assert_eq!(
+            options.read_auto_framing(&mut file).unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+
+    /// A file without the magic bytes anywhere shouldn't be guessed to have any framing
+    #[test]
+    fn detects_unknown_framing() {
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(
+            options.detect_framing(&mut Cursor::new(binary())).unwrap(),
+            Framing::Unknown
+        );
+    }
+
+    /// Arbitrary tails, including ones which would overflow when followed as a footer, should
+    /// never panic, and should only match a framing if the magic bytes are really there
+    #[test]
+    fn detects_framing_with_garbage_tails() {
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        for tail in [[0xff; 8], [0xfe; 8], [0x80; 8], [0; 8]] {
+            let mut input_data = binary();
+            input_data.extend(tail);
+            assert_eq!(
+                options
+                    .detect_framing(&mut Cursor::new(&input_data))
+                    .unwrap(),
+                Framing::Unknown
+            );
+
+            // A zero length right after the magic bytes is a valid empty payload with a length
+            // footer, so that one can't be told apart from a suffix
+            let expected = match tail {
+                [0, ..] => Framing::MagicAndLength,
+                _ => Framing::Suffix,
+            };
+            let mut input_data = binary();
+            input_data.extend([1, 2, 3, 4]);
+            input_data.extend(tail);
+            assert_eq!(
+                options
+                    .detect_framing(&mut Cursor::new(&input_data))
+                    .unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(
+            options.detect_framing(&mut Cursor::new([0xff; 3])).unwrap(),
+            Framing::Unknown
+        );
+    }
+}
@@ -0,0 +1,210 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{error, fmt, io, ops::Range, str};
+
+/// Errors which can occur when reading configuration beyond plain IO errors
+///
+/// Methods which return [`io::Result`] carry these inside of the [`io::Error`] they return, and
+/// they can be recovered by converting the error with [`CatConfError::from`]
+///
+/// # Example
+///
+/// ```
+/// use catconf::{CatConfError, ConfReaderOptions};
+///
+/// # let mut buff = vec![0; 4096];
+/// # buff.extend(b"CATCONF");
+/// # buff.extend([0xff; 16]);
+/// # let mut input = std::io::Cursor::new(&buff);
+/// let err = ConfReaderOptions::new(b"CATCONF".to_vec())
+///     .require_utf8(true)
+///     .read(&mut input)
+///     .unwrap_err();
+///
+/// assert!(matches!(CatConfError::from(err), CatConfError::Utf8(_)));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CatConfError {
+    /// Error from the underlying input file
+    Io(io::Error),
+    /// The magic bytes were not found anywhere in the file, such as when no configuration has
+    /// been appended to it
+    NotFound,
+    /// The payload was required to be UTF-8, but it was not
+    Utf8(str::Utf8Error),
+    /// The payload started with a UTF-16 byte order mark, but was not valid UTF-16
+    Utf16,
+    /// The checksum stored with the payload did not match the payload
+    ChecksumMismatch {
+        /// Checksum stored with the payload
+        expected: u32,
+        /// Checksum of the payload that was read
+        found: u32,
+    },
+    /// The SHA-256 hash of the whole file did not match the hash expected
+    IntegrityMismatch {
+        /// Hash the file was expected to have
+        expected: [u8; 32],
+        /// Hash of the file that was read
+        found: [u8; 32],
+    },
+    /// The magic bytes were rejected by [`validate_magic`](crate::validate_magic), for the
+    /// reason given
+    InvalidMagic(&'static str),
+    /// The payload was not within the length range expected
+    LengthOutOfRange {
+        /// Length of the payload found
+        len: u64,
+        /// Range of lengths the payload was expected to be in
+        range: Range<u64>,
+    },
+    /// The deadline given to
+    /// [`ConfReaderOptions::read_with_deadline`](crate::ConfReaderOptions::read_with_deadline)
+    /// passed before the configuration was found
+    DeadlineExceeded,
+    /// The payload could not be parsed as JSON
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// The payload could not be parsed as MessagePack
+    #[cfg(feature = "msgpack")]
+    MessagePack(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for CatConfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatConfError::Io(e) => write!(f, "io error: {e}"),
+            CatConfError::NotFound => {
+                write!(
+                    f,
+                    "reached beginning of the file without finding magic bytes"
+                )
+            }
+            CatConfError::Utf8(e) => write!(f, "payload is not valid UTF-8: {e}"),
+            CatConfError::Utf16 => write!(f, "payload is not valid UTF-16"),
+            CatConfError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "payload checksum {found:#010x} does not match stored checksum {expected:#010x}"
+            ),
+            CatConfError::IntegrityMismatch { expected, found } => write!(
+                f,
+                "file hash {} does not match expected hash {}",
+                hex(found),
+                hex(expected)
+            ),
+            CatConfError::InvalidMagic(reason) => write!(f, "invalid magic bytes: {reason}"),
+            CatConfError::LengthOutOfRange { len, range } => write!(
+                f,
+                "payload length {len} is outside of the expected range {}..{}",
+                range.start, range.end
+            ),
+            CatConfError::DeadlineExceeded => {
+                write!(f, "deadline passed before the configuration was found")
+            }
+            #[cfg(feature = "json")]
+            CatConfError::Json(e) => write!(f, "payload is not valid JSON: {e}"),
+            #[cfg(feature = "msgpack")]
+            CatConfError::MessagePack(e) => write!(f, "payload is not valid MessagePack: {e}"),
+        }
+    }
+}
+
+/// Internal function used to show a hash in errors
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+impl error::Error for CatConfError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CatConfError::Io(e) => Some(e),
+            CatConfError::NotFound => None,
+            CatConfError::Utf8(e) => Some(e),
+            CatConfError::Utf16 => None,
+            CatConfError::ChecksumMismatch { .. } => None,
+            CatConfError::IntegrityMismatch { .. } => None,
+            CatConfError::InvalidMagic(_) => None,
+            CatConfError::LengthOutOfRange { .. } => None,
+            CatConfError::DeadlineExceeded => None,
+            #[cfg(feature = "json")]
+            CatConfError::Json(e) => Some(e),
+            #[cfg(feature = "msgpack")]
+            CatConfError::MessagePack(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for CatConfError {
+    fn from(err: io::Error) -> Self {
+        if err
+            .get_ref()
+            .is_some_and(|inner| inner.is::<CatConfError>())
+        {
+            if let Some(Ok(inner)) = err.into_inner().map(|inner| inner.downcast()) {
+                return *inner;
+            }
+            unreachable!("inner error was checked to be a CatConfError");
+        }
+
+        CatConfError::Io(err)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for CatConfError {
+    fn from(err: serde_json::Error) -> Self {
+        CatConfError::Json(err)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::decode::Error> for CatConfError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        CatConfError::MessagePack(err)
+    }
+}
+
+impl From<CatConfError> for io::Error {
+    fn from(err: CatConfError) -> Self {
+        match err {
+            CatConfError::Io(e) => e,
+            CatConfError::NotFound => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+            CatConfError::DeadlineExceeded => io::Error::new(io::ErrorKind::TimedOut, err),
+            _ => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converting to an io::Error and back should give back the original error
+    #[test]
+    fn round_trips_through_io_error() {
+        let invalid = vec![0xff];
+        let utf8_err = str::from_utf8(&invalid).unwrap_err();
+        let err = io::Error::from(CatConfError::Utf8(utf8_err));
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(CatConfError::from(err), CatConfError::Utf8(_)));
+
+        let err = io::Error::new(io::ErrorKind::NotFound, "not found");
+        assert!(matches!(CatConfError::from(err), CatConfError::Io(_)));
+    }
+}
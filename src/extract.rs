@@ -0,0 +1,247 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*, SeekFrom};
+
+use crate::{ConfReaderOptions, PayloadLocation};
+
+/// Size of the chunks copied at a time when extracting a payload
+const EXTRACT_CHUNK_SIZE: usize = 8192;
+
+/// Keeps track of how much of a payload has been extracted, so that a large payload can be
+/// pulled out a chunk at a time without holding on to the input file in between
+///
+/// Created with [`ConfReaderOptions::extract_cursor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractCursor {
+    location: PayloadLocation,
+    emitted: u64,
+}
+
+impl ExtractCursor {
+    /// Reads the next chunk of the payload into the buffer, returning how many bytes were read.
+    /// Once the whole payload has been read, this returns 0
+    ///
+    /// The input should be the same file that was used to create the cursor
+    pub fn read_more<F>(&mut self, input: &mut F, buf: &mut [u8]) -> io::Result<usize>
+    where
+        F: Seek + Read,
+    {
+        let max = buf
+            .len()
+            .min(usize::try_from(self.remaining()).unwrap_or(usize::MAX));
+
+        if max == 0 {
+            return Ok(0);
+        }
+
+        input.seek(SeekFrom::Start(self.location.offset + self.emitted))?;
+        let bytes_read = input.read(&mut buf[..max])?;
+        self.emitted += bytes_read as u64;
+
+        Ok(bytes_read)
+    }
+
+    /// Length of the whole payload
+    pub fn len(&self) -> u64 {
+        self.location.len
+    }
+
+    /// Whether the payload is empty
+    pub fn is_empty(&self) -> bool {
+        self.location.len == 0
+    }
+
+    /// How many bytes of the payload have been read so far
+    pub fn position(&self) -> u64 {
+        self.emitted
+    }
+
+    /// How many bytes of the payload are left to be read
+    pub fn remaining(&self) -> u64 {
+        self.location.len - self.emitted
+    }
+}
+
+impl ConfReaderOptions {
+    /// Locates the configuration and returns a cursor to extract it incrementally with
+    /// [`ExtractCursor::read_more`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let mut cursor = ConfReaderOptions::new(b"CATCONF".to_vec()).extract_cursor(&mut input)?;
+    ///
+    /// let mut chunk = [0; 2];
+    /// while cursor.read_more(&mut input, &mut chunk)? > 0 {
+    ///     // Do something with the chunk, maybe yield to other tasks
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_cursor<F>(&self, input: &mut F) -> io::Result<ExtractCursor>
+    where
+        F: Seek + Read,
+    {
+        let location = self.with_input(input, |input| self.locate_once(input))?;
+
+        Ok(ExtractCursor {
+            location,
+            emitted: 0,
+        })
+    }
+
+    /// Locates the configuration and copies it into the destination a chunk at a time, without
+    /// holding the whole payload in memory. Returns the number of bytes written
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut exe = std::fs::File::open(std::env::current_exe()?)?;
+    /// let mut dest = std::fs::File::create("conf.sqlite")?;
+    /// ConfReaderOptions::new(b"CATCONF".to_vec()).extract_to(&mut exe, &mut dest)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_to<F, W>(&self, input: &mut F, dest: &mut W) -> io::Result<u64>
+    where
+        F: Seek + Read,
+        W: Write,
+    {
+        self.extract_to_with_progress(input, dest, |_, _| {})
+    }
+
+    /// Same as [`ConfReaderOptions::extract_to`], but calls the progress function after each
+    /// chunk is written with the number of bytes written so far and the total length of the
+    /// payload
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut exe = std::fs::File::open(std::env::current_exe()?)?;
+    /// let mut dest = std::fs::File::create("conf.sqlite")?;
+    /// ConfReaderOptions::new(b"CATCONF".to_vec()).extract_to_with_progress(
+    ///     &mut exe,
+    ///     &mut dest,
+    ///     |written, total| eprint!("\r{written}/{total}"),
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_to_with_progress<F, W, P>(
+        &self,
+        input: &mut F,
+        dest: &mut W,
+        mut progress: P,
+    ) -> io::Result<u64>
+    where
+        F: Seek + Read,
+        W: Write,
+        P: FnMut(u64, u64),
+    {
+        let mut cursor = self.extract_cursor(input)?;
+        let mut chunk = vec![0; EXTRACT_CHUNK_SIZE];
+
+        while cursor.remaining() > 0 {
+            let bytes_read = cursor.read_more(input, &mut chunk)?;
+
+            if bytes_read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "input file ended before the end of the payload",
+                ));
+            }
+
+            dest.write_all(&chunk[..bytes_read])?;
+            progress(cursor.position(), cursor.len());
+        }
+
+        dest.flush()?;
+
+        Ok(cursor.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Extracting a chunk at a time should give the same bytes as reading it all at once
+    #[test]
+    fn extracts_in_chunks() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..30).map(|i| (i % 5) as u8 + 10));
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = Cursor::new(&input_data);
+        let full = options.read(&mut buf).unwrap();
+
+        let mut cursor = options.extract_cursor(&mut buf).unwrap();
+        let mut extracted = Vec::<u8>::new();
+        let mut chunk = [0; 10];
+
+        for _ in 0..3 {
+            let bytes_read = cursor.read_more(&mut buf, &mut chunk).unwrap();
+            extracted.extend(&chunk[..bytes_read]);
+        }
+
+        assert_eq!(cursor.remaining(), 0);
+        assert_eq!(cursor.read_more(&mut buf, &mut chunk).unwrap(), 0);
+        assert_eq!(extracted, full);
+    }
+
+    /// Progress should be reported for every chunk, ending at the total length
+    #[test]
+    fn reports_extraction_progress() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..20_000).map(|i| (i % 5) as u8 + 10));
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = Cursor::new(&input_data);
+        let mut dest = vec![];
+        let mut updates = vec![];
+
+        let written = options
+            .extract_to_with_progress(&mut buf, &mut dest, |written, total| {
+                updates.push((written, total))
+            })
+            .unwrap();
+
+        assert_eq!(written, 20_000);
+        assert_eq!(dest, input_data[104..]);
+        assert!(updates.len() > 1);
+        assert!(updates.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(updates.last(), Some(&(20_000, 20_000)));
+    }
+}
@@ -0,0 +1,308 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*, SeekFrom};
+
+use crate::PayloadLocation;
+
+/// The different ways the configuration can be laid out after the original binary, as
+/// reported by [`ConfReaderOptions::detect_framing`](crate::ConfReaderOptions::detect_framing)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Framing {
+    /// `binary || magic bytes || configuration`, found by scanning for the magic bytes
+    Suffix,
+    /// `binary || magic bytes || configuration || u64 offset of magic bytes`
+    OffsetPointer,
+    /// `binary || magic bytes || configuration || u64 length of configuration`
+    MagicAndLength,
+    /// `stub || u32 length of configuration || configuration`, found at a fixed offset from
+    /// the start of the file rather than by the magic bytes
+    LengthPrefix,
+    /// Named sections followed by an index, as written by
+    /// [`ConfWriterOptions::write_sections`](crate::ConfWriterOptions::write_sections)
+    Sections,
+    /// None of the other framings could be found in the file
+    Unknown,
+}
+
+/// Internal function used to report a framing which a single payload can't be read from or
+/// written with
+pub(crate) fn unsupported_framing(framing: Framing) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("a single payload can't be located with {framing:?} framing"),
+    )
+}
+
+/// Size of the `u64` footers used by some of the framings
+pub(crate) const FOOTER_LEN: u64 = 8;
+
+/// Largest number of bytes a `u64` takes up as a varint
+pub(crate) const VARINT_MAX_LEN: u64 = 10;
+
+/// How the length of the configuration is stored at the end of the file when using
+/// [`ConfReaderOptions::magic_and_length`](crate::ConfReaderOptions::magic_and_length)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthEncoding {
+    /// A little endian `u64`, always taking up 8 bytes
+    #[default]
+    U64,
+    /// A LEB128 varint with its bytes in reverse order, taking up between 1 and 10 bytes
+    ///
+    /// The length is split into groups of 7 bits, least significant first, and each group
+    /// is stored in a byte which has its high bit set if there are more significant groups.
+    /// These bytes are then written in reverse, so the last byte of the file holds the least
+    /// significant group, and reading backward from the end of the file stops at the first
+    /// byte without its high bit set. For example, a length of 300 is the LEB128 bytes
+    /// `ac 02`, and is written as `02 ac`
+    Varint,
+}
+
+impl LengthEncoding {
+    /// Internal method used to encode a length for the end of the file
+    pub(crate) fn encode(self, len: u64) -> Vec<u8> {
+        match self {
+            LengthEncoding::U64 => len.to_le_bytes().to_vec(),
+            LengthEncoding::Varint => {
+                let mut bytes = vec![];
+                let mut remaining = len;
+
+                loop {
+                    let group = (remaining & 0x7f) as u8;
+                    remaining >>= 7;
+
+                    if remaining == 0 {
+                        bytes.push(group);
+                        break;
+                    }
+
+                    bytes.push(group | 0x80);
+                }
+
+                bytes.reverse();
+                bytes
+            }
+        }
+    }
+
+    /// Internal method used to find the most bytes the length could take up
+    pub(crate) fn max_len(self) -> u64 {
+        match self {
+            LengthEncoding::U64 => FOOTER_LEN,
+            LengthEncoding::Varint => VARINT_MAX_LEN,
+        }
+    }
+}
+
+/// Internal function used to find the payload by following the offset pointer at the end of
+/// the file
+pub(crate) fn locate_offset_pointer<F>(
+    magic_bytes: &[u8],
+    input: &mut F,
+) -> io::Result<PayloadLocation>
+where
+    F: Seek + Read + ?Sized,
+{
+    let footer_start = input.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    let magic_offset = read_u64(input)?;
+    let location = offset_pointer_location(magic_bytes.len() as u64, magic_offset, footer_start)?;
+
+    input.seek(SeekFrom::Start(magic_offset))?;
+    verify_magic(
+        magic_bytes,
+        input,
+        "magic bytes not found at the offset pointer",
+    )?;
+
+    Ok(location)
+}
+
+/// Internal function used to check the offset pointer read from the footer starting at
+/// `footer_start` against the file, before it is followed. The pointer comes straight from
+/// the file, so it can be anything
+pub(crate) fn offset_pointer_location(
+    magic_len: u64,
+    magic_offset: u64,
+    footer_start: u64,
+) -> io::Result<PayloadLocation> {
+    let offset = magic_offset
+        .checked_add(magic_len)
+        .filter(|offset| *offset <= footer_start)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "offset pointer points past the end of the file",
+            )
+        })?;
+
+    Ok(PayloadLocation {
+        magic_offset,
+        offset,
+        len: footer_start - offset,
+    })
+}
+
+/// Internal function used to decode a [`LengthEncoding::Varint`] from the end of a buffer
+/// holding the end of the file, returning the length and how many bytes it took up
+pub(crate) fn decode_varint(tail: &[u8]) -> io::Result<(u64, u64)> {
+    let malformed = || io::Error::new(io::ErrorKind::InvalidData, "payload length is malformed");
+    let mut len: u64 = 0;
+
+    for (i, byte) in tail.iter().rev().take(VARINT_MAX_LEN as usize).enumerate() {
+        let group = u64::from(byte & 0x7f);
+        let shift = 7 * i as u32;
+
+        if shift == 63 && group > 1 {
+            return Err(malformed());
+        }
+        len |= group << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok((len, i as u64 + 1));
+        }
+    }
+
+    Err(malformed())
+}
+
+/// Internal function used to find the payload from the length in front of it, directly after
+/// the stub at the start of the file
+pub(crate) fn locate_length_prefix<F>(stub_len: u64, input: &mut F) -> io::Result<PayloadLocation>
+where
+    F: Seek + Read + ?Sized,
+{
+    let file_len = input.seek(SeekFrom::End(0))?;
+
+    input.seek(SeekFrom::Start(stub_len))?;
+    let len = u64::from(read_u32(input)?);
+    let offset = stub_len + 4;
+
+    if offset + len > file_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "payload length is longer than the file",
+        ));
+    }
+
+    Ok(PayloadLocation {
+        magic_offset: stub_len,
+        offset,
+        len,
+    })
+}
+
+/// Internal function used to find the payload by following the length at the end of the
+/// file back to the magic bytes
+pub(crate) fn locate_magic_and_length<F>(
+    magic_bytes: &[u8],
+    encoding: LengthEncoding,
+    input: &mut F,
+) -> io::Result<PayloadLocation>
+where
+    F: Seek + Read + ?Sized,
+{
+    let (len, footer_start) = match encoding {
+        LengthEncoding::U64 => {
+            let footer_start = input.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+            (read_u64(input)?, footer_start)
+        }
+        LengthEncoding::Varint => {
+            let file_len = input.seek(SeekFrom::End(0))?;
+            let tail_len = VARINT_MAX_LEN.min(file_len);
+            let mut tail = vec![0; tail_len as usize];
+            input.seek(SeekFrom::Start(file_len - tail_len))?;
+            input.read_exact(&mut tail)?;
+
+            let (len, footer_len) = decode_varint(&tail)?;
+            (len, file_len - footer_len)
+        }
+    };
+    let location = magic_and_length_location(magic_bytes.len() as u64, len, footer_start)?;
+
+    input.seek(SeekFrom::Start(location.magic_offset))?;
+    verify_magic(
+        magic_bytes,
+        input,
+        "magic bytes not found before the payload length given",
+    )?;
+
+    Ok(location)
+}
+
+/// Internal function used to check the length read from the footer starting at
+/// `footer_start` against the file, before it is followed back to the magic bytes
+pub(crate) fn magic_and_length_location(
+    magic_len: u64,
+    len: u64,
+    footer_start: u64,
+) -> io::Result<PayloadLocation> {
+    let magic_offset = footer_start
+        .checked_sub(len)
+        .and_then(|offset| offset.checked_sub(magic_len))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "payload length is longer than the file",
+            )
+        })?;
+
+    Ok(PayloadLocation {
+        magic_offset,
+        offset: footer_start - len,
+        len,
+    })
+}
+
+/// Internal function used to read one of the little endian `u64` footers
+pub(crate) fn read_u64<F: Read + ?Sized>(input: &mut F) -> io::Result<u64> {
+    let mut buffer = [0; FOOTER_LEN as usize];
+    input.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+/// Internal function used to read a little endian `u32`, such as a checksum
+pub(crate) fn read_u32<F: Read + ?Sized>(input: &mut F) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    input.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
+
+/// Internal function used to make sure the magic bytes are where the framing says they are
+pub(crate) fn verify_magic<F: Read + ?Sized>(
+    magic_bytes: &[u8],
+    input: &mut F,
+    message: &'static str,
+) -> io::Result<()> {
+    let mut buffer = vec![0; magic_bytes.len()];
+    input.read_exact(&mut buffer)?;
+
+    check_magic(magic_bytes, &buffer, message)
+}
+
+/// Internal function used to compare the bytes read where the framing says the magic bytes
+/// are against them
+pub(crate) fn check_magic(
+    magic_bytes: &[u8],
+    buffer: &[u8],
+    message: &'static str,
+) -> io::Result<()> {
+    if buffer != magic_bytes {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    Ok(())
+}
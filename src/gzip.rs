@@ -0,0 +1,211 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*, Cursor};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{framing::Framing, ConfReaderOptions};
+
+impl ConfReaderOptions {
+    /// Reads the configuration from a gzip stream of the whole file, for when the binary and
+    /// configuration were compressed together and the stream can't be seeked. The stream is
+    /// decompressed a window at a time, keeping only the end of it, and the configuration is
+    /// the payload after the last copy of the magic bytes once the stream ends
+    ///
+    /// The magic bytes must be within the last `payload_cap` bytes of the stream plus the
+    /// length of the magic bytes, otherwise this fails with [`CatConfError::NotFound`](crate::CatConfError::NotFound) as if
+    /// there was no configuration. The payload is picked and checked the same way as
+    /// [`ConfReaderOptions::read`] would, treating the end of the stream as the whole file. At
+    /// most twice that plus the window size is held in memory at a time, no matter how large
+    /// the decompressed stream is
+    ///
+    /// Only the default framing is supported, as the footer framings need seeking to be
+    /// useful
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let artifact = std::fs::File::open("app.gz")?;
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_from_gzip_stream(artifact, 64 * 1024)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_from_gzip_stream<R: Read>(
+        &self,
+        input: R,
+        payload_cap: usize,
+    ) -> io::Result<Vec<u8>> {
+        if self.framing_opt != Framing::Suffix {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only the default framing can be read from a gzip stream",
+            ));
+        }
+
+        let tail = self.gzip_stream_tail(MultiGzDecoder::new(input), payload_cap)?;
+
+        // The end of the stream which was kept is treated as the whole file, so the payload is
+        // picked and checked exactly as it would be by read
+        let (conf, checksum) = self.read_once(&mut Cursor::new(tail))?;
+
+        self.finish_payload(conf, checksum)
+    }
+
+    /// Internal method used to decompress the stream, returning only the bytes at the end of
+    /// it which could hold the magic bytes and the payload. Whole multiples of the magic
+    /// alignment are dropped from the front, so that offsets into what is kept line up with
+    /// offsets into the stream
+    fn gzip_stream_tail<R: Read>(&self, mut stream: R, payload_cap: usize) -> io::Result<Vec<u8>> {
+        let keep = payload_cap + self.magic_bytes_opt.len();
+        // Sized the same as the scan windows, which are never empty, so a read of the stream
+        // only returns nothing at its end
+        let mut chunk = vec![0; self.scan().window_size as usize];
+        let mut tail = Vec::with_capacity(keep * 2);
+        let alignment = self.aligned_magic_opt as usize;
+        let aligned_excess = |excess: usize| excess - excess % alignment;
+
+        loop {
+            let bytes_read = match stream.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(bytes_read) => bytes_read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            tail.extend_from_slice(&chunk[..bytes_read]);
+
+            // Only drop the front once there's plenty of it, so that each byte is moved at
+            // most a couple of times
+            if tail.len() > keep * 2 {
+                tail.drain(..aligned_excess(tail.len() - keep));
+            }
+        }
+
+        if tail.len() > keep {
+            tail.drain(..aligned_excess(tail.len() - keep));
+        }
+
+        Ok(tail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use crate::{text, CatConfError};
+
+    use super::*;
+
+    /// Compresses the whole of a file
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Configuration should be found at the end of a compressed binary, but not if it is
+    /// further back than the cap allows
+    #[test]
+    fn reads_gzip_stream() {
+        let mut input_data = (0..100_000)
+            .map(|i| (i % 200) as u8 + 5)
+            .collect::<Vec<_>>();
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 300]);
+        let compressed = gzip(&input_data);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(64);
+
+        let conf = options
+            .read_from_gzip_stream(Cursor::new(&compressed), 1000)
+            .unwrap();
+        assert_eq!(conf, [5; 300]);
+
+        let err = options
+            .read_from_gzip_stream(Cursor::new(&compressed), 100)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// A window size of 0 should be raised the same as for read, rather than never
+    /// decompressing anything
+    #[test]
+    fn reads_gzip_stream_with_zero_window() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(b"conf");
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(0);
+
+        assert_eq!(
+            options.read(&mut Cursor::new(&input_data)).unwrap(),
+            b"conf"
+        );
+        assert_eq!(
+            options
+                .read_from_gzip_stream(Cursor::new(gzip(&input_data)), 100)
+                .unwrap(),
+            b"conf"
+        );
+    }
+
+    /// A compressed file should give the same payload and errors as reading it uncompressed,
+    /// including the BOM being stripped, the checksum being validated and a missing copy of
+    /// the magic bytes being reported as not found
+    #[test]
+    fn reads_gzip_stream_like_read() {
+        let mut conf = text::UTF8_BOM.to_vec();
+        conf.extend([5; 100]);
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(&conf);
+        input_data.extend(crate::checksum::crc32(&conf).to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16).checksum(true).strip_bom(true);
+
+        let read = options.read(&mut Cursor::new(&input_data)).unwrap();
+        let gzip_read = options
+            .read_from_gzip_stream(Cursor::new(gzip(&input_data)), 1000)
+            .unwrap();
+        assert_eq!(gzip_read, read);
+        assert_eq!(gzip_read, [5; 100]);
+
+        let last = input_data.len() - 1;
+        input_data[last] ^= 0xff;
+        let err = options
+            .read_from_gzip_stream(Cursor::new(gzip(&input_data)), 1000)
+            .unwrap_err();
+        assert!(matches!(
+            err.into_inner()
+                .unwrap()
+                .downcast::<CatConfError>()
+                .as_deref(),
+            Ok(CatConfError::ChecksumMismatch { .. })
+        ));
+
+        let err = options
+            .read_from_gzip_stream(Cursor::new(gzip(&[5; 1000])), 100)
+            .unwrap_err();
+        assert!(crate::is_not_found(&err));
+    }
+}
@@ -0,0 +1,108 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*, SeekFrom};
+
+use sha2::{Digest, Sha256};
+
+use crate::{CatConfError, ConfReaderOptions};
+
+/// Amount of the file hashed at a time by [`ConfReaderOptions::verify_file_integrity`]
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+impl ConfReaderOptions {
+    /// Hashes the whole file with SHA-256, the original binary and the configuration
+    /// together, and checks it against the hash expected such as from a release manifest.
+    /// This attests to the artifact as a whole, and doesn't look for the configuration at all;
+    /// the file is read from start to end once, a chunk at a time
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let expected = [0; 32];
+    /// let mut exe = std::fs::File::open(std::env::current_exe()?)?;
+    /// ConfReaderOptions::new(b"CATCONF".to_vec()).verify_file_integrity(&mut exe, expected)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_file_integrity<F>(
+        &self,
+        input: &mut F,
+        expected: [u8; 32],
+    ) -> Result<(), CatConfError>
+    where
+        F: Seek + Read,
+    {
+        let found = self.with_input(input, |input| {
+            let mut hasher = Sha256::new();
+            let mut chunk = vec![0; HASH_CHUNK_SIZE];
+            input.seek(SeekFrom::Start(0))?;
+
+            loop {
+                match input.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(bytes_read) => hasher.update(&chunk[..bytes_read]),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            Ok(<[u8; 32]>::from(hasher.finalize()))
+        })?;
+
+        if found != expected {
+            return Err(CatConfError::IntegrityMismatch { expected, found });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// The hash should cover the whole file, so changing the binary or the configuration
+    /// should both be caught
+    #[test]
+    fn verifies_file_integrity() {
+        let mut input_data = (0..200_000)
+            .map(|i| (i % 200) as u8 + 5)
+            .collect::<Vec<_>>();
+        input_data.extend(b"CATCONFconf");
+        let expected = <[u8; 32]>::from(Sha256::digest(&input_data));
+        let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+
+        options
+            .verify_file_integrity(&mut Cursor::new(&input_data), expected)
+            .unwrap();
+
+        for i in [0, input_data.len() - 1] {
+            let mut changed = input_data.clone();
+            changed[i] ^= 1;
+
+            assert!(matches!(
+                options.verify_file_integrity(&mut Cursor::new(&changed), expected),
+                Err(CatConfError::IntegrityMismatch { expected: e, .. }) if e == expected
+            ));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::prelude::*;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{CatConfError, ConfReaderOptions};
+
+impl ConfReaderOptions {
+    /// Reads the configuration and parses it as JSON
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(br#"CATCONF{"port": 8080}"#);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf: serde_json::Value = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_json(&mut input)?;
+    /// # assert_eq!(conf["port"], 8080);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_json<T, F>(&self, input: &mut F) -> Result<T, CatConfError>
+    where
+        T: DeserializeOwned,
+        F: Seek + Read,
+    {
+        Ok(self.read_json_with_raw(input)?.0)
+    }
+
+    /// Reads the configuration and parses it as JSON, but also returns the exact bytes that
+    /// were parsed, such as for logging the original configuration
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(br#"CATCONF{"port": 8080}"#);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let (conf, raw): (serde_json::Value, _) = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_json_with_raw(&mut input)?;
+    /// # assert_eq!(conf["port"], 8080);
+    /// # assert_eq!(raw, br#"{"port": 8080}"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_json_with_raw<T, F>(&self, input: &mut F) -> Result<(T, Vec<u8>), CatConfError>
+    where
+        T: DeserializeOwned,
+        F: Seek + Read,
+    {
+        let raw = self.read(input)?;
+        let conf = serde_json::from_slice(&raw)?;
+
+        Ok((conf, raw))
+    }
+
+    /// Reads every block of configuration in the file as JSON, and merges them together into
+    /// one value. Blocks appended later take precedence over the ones before them: objects are
+    /// merged key by key, and any other value is replaced outright
+    ///
+    /// See [`ConfReaderOptions::read_blocks`] for how the blocks are found. As the whole file is
+    /// scanned, a copy of the magic bytes inside the original binary shows up as an innermost
+    /// block of whatever binary data follows it. So blocks are parsed from the outermost in,
+    /// and the first block which isn't a JSON object after at least one that is, along with
+    /// every block inside of it, is taken to be part of the binary and left out. The outermost
+    /// block always has to parse
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(br#"CATCONF{"port": 80, "host": "localhost"}"#);
+    /// # buff.extend(br#"CATCONF{"port": 8080}"#);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf: serde_json::Value = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_merged_json(&mut input)?;
+    /// # assert_eq!(conf["port"], 8080);
+    /// # assert_eq!(conf["host"], "localhost");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_merged_json<T, F>(&self, input: &mut F) -> Result<T, CatConfError>
+    where
+        T: DeserializeOwned,
+        F: Seek + Read,
+    {
+        let mut values = vec![];
+
+        for block in self.read_blocks(input)? {
+            match serde_json::from_slice::<Value>(&block) {
+                Ok(value @ Value::Object(_)) => values.push(value),
+                _ if !values.is_empty() => break,
+                Ok(value) => values.push(value),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut merged = Value::Null;
+        for value in values.into_iter().rev() {
+            merge(&mut merged, value);
+        }
+
+        Ok(serde_json::from_value(merged)?)
+    }
+}
+
+/// Internal function used to deep merge the override value on top of the base value
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Conf {
+        name: String,
+        port: u16,
+        tls: Tls,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Tls {
+        enabled: bool,
+        cert: String,
+    }
+
+    /// Keys from the override block should win, while keys it doesn't mention are left alone,
+    /// even in nested objects
+    #[test]
+    fn override_block_wins() {
+        let mut input_data = vec![0; 64];
+        input_data.extend(b"CATCONF");
+        input_data.extend(br#"{"name":"base","port":80,"tls":{"enabled":false,"cert":"a.pem"}}"#);
+        input_data.extend(b"CATCONF");
+        input_data.extend(br#"{"port":443,"tls":{"enabled":true}}"#);
+
+        let mut buf = Cursor::new(&input_data);
+        let conf: Conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+            .read_merged_json(&mut buf)
+            .unwrap();
+
+        assert_eq!(
+            conf,
+            Conf {
+                name: "base".into(),
+                port: 443,
+                tls: Tls {
+                    enabled: true,
+                    cert: "a.pem".into(),
+                },
+            }
+        );
+    }
+
+    /// A copy of the magic bytes inside the binary shouldn't stop the blocks appended after it
+    /// from being merged
+    #[test]
+    fn ignores_stray_magic_in_binary() {
+        let mut input_data = b"\x7fELF".to_vec();
+        input_data.extend([0; 60]);
+        input_data.extend(b"CATCONF");
+        input_data.extend([0x90, 0xc3, 0x00, 0x7b, 0xff]);
+        input_data.extend([0; 60]);
+        input_data.extend(b"CATCONF");
+        input_data.extend(br#"{"name":"base","port":80,"tls":{"enabled":false,"cert":"a.pem"}}"#);
+        input_data.extend(b"CATCONF");
+        input_data.extend(br#"{"port":443}"#);
+
+        let conf: Conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+            .read_merged_json(&mut Cursor::new(&input_data))
+            .unwrap();
+
+        assert_eq!(conf.name, "base");
+        assert_eq!(conf.port, 443);
+
+        input_data.truncate(input_data.len() - 3);
+        assert!(matches!(
+            ConfReaderOptions::new(b"CATCONF".to_vec())
+                .read_merged_json::<Conf, _>(&mut Cursor::new(&input_data))
+                .unwrap_err(),
+            CatConfError::Json(_)
+        ));
+    }
+
+    /// The raw bytes returned should be exactly what was parsed into the configuration
+    #[test]
+    fn returns_raw_bytes() {
+        let raw = br#"{"name":"app","port":80,"tls":{"enabled":true,"cert":"a.pem"}}"#;
+        let mut input_data = vec![0; 64];
+        input_data.extend(b"CATCONF");
+        input_data.extend(raw);
+
+        let mut buf = Cursor::new(&input_data);
+        let (conf, read_raw): (Conf, _) = ConfReaderOptions::new(b"CATCONF".to_vec())
+            .read_json_with_raw(&mut buf)
+            .unwrap();
+
+        assert_eq!(read_raw, raw);
+        assert_eq!(serde_json::from_slice::<Conf>(&read_raw).unwrap(), conf);
+        assert_eq!(conf.port, 80);
+    }
+}
@@ -0,0 +1,181 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    io::{self, prelude::*, SeekFrom},
+};
+
+use crate::{
+    checksum::ChecksumMode,
+    framing::{self, Framing, FOOTER_LEN},
+    read_payload,
+    scan::locate_all_payloads,
+    ConfReaderOptions,
+};
+
+/// Something unexpected noticed by [`ConfReaderOptions::read_lenient`] which didn't stop the
+/// configuration from being read
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The magic bytes appear more than once in the file, so either configuration was appended
+    /// more than once or the original binary contains a copy of the magic bytes
+    MultipleMagic {
+        /// Number of copies of the magic bytes found
+        count: usize,
+    },
+    /// There are bytes after the configuration which aren't part of it
+    TrailingBytes {
+        /// Number of bytes after the configuration
+        len: u64,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::MultipleMagic { count } => {
+                write!(f, "magic bytes appear {count} times in the file")
+            }
+            Warning::TrailingBytes { len } => {
+                write!(f, "{len} bytes follow the configuration")
+            }
+        }
+    }
+}
+
+impl ConfReaderOptions {
+    /// Reads the configuration the same as [`ConfReaderOptions::read`], but also checks the
+    /// file for anything suspicious and returns it as a list of warnings rather than failing.
+    /// This is useful in production for logging problems without refusing to start
+    ///
+    /// Checking for multiple copies of the magic bytes means scanning the whole file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, Warning};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFold");
+    /// # buff.extend([0; 4096]);
+    /// # buff.extend(b"CATCONFnew");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let (conf, warnings) = ConfReaderOptions::new(b"CATCONF".to_vec()).read_lenient(&mut input)?;
+    ///
+    /// for warning in &warnings {
+    ///     eprintln!("warning: {warning}");
+    /// }
+    /// # assert_eq!(conf, b"new");
+    /// # assert_eq!(warnings, [Warning::MultipleMagic { count: 2 }]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_lenient<F>(&self, input: &mut F) -> io::Result<(Vec<u8>, Vec<Warning>)>
+    where
+        F: Seek + Read,
+    {
+        let (conf, checksum, warnings) = self.with_input(input, |input| {
+            let location = self.locate_once(input)?;
+            let conf = read_payload(input, location)?;
+
+            let checksum = match self.checksum_opt {
+                ChecksumMode::Validate => Some(framing::read_u32(input)?),
+                ChecksumMode::SkipValidation => {
+                    input.seek(SeekFrom::Current(4))?;
+                    None
+                }
+                ChecksumMode::Absent => None,
+            };
+
+            let mut warnings = vec![];
+            let payload_end = input.stream_position()?;
+            let footer_len = match self.framing_opt {
+                Framing::Suffix | Framing::LengthPrefix | Framing::Sections | Framing::Unknown => 0,
+                Framing::OffsetPointer => FOOTER_LEN,
+                Framing::MagicAndLength => self
+                    .length_encoding_opt
+                    .encode(payload_end - location.offset)
+                    .len() as u64,
+            };
+            let frame_end = payload_end + footer_len;
+            let file_len = input.seek(SeekFrom::End(0))?;
+
+            if file_len > frame_end {
+                warnings.push(Warning::TrailingBytes {
+                    len: file_len - frame_end,
+                });
+            }
+
+            let count = locate_all_payloads(&self.scan(), input)?.len();
+
+            if count > 1 {
+                warnings.push(Warning::MultipleMagic { count });
+            }
+
+            Ok((conf, checksum, warnings))
+        })?;
+
+        self.check_payload(&conf, checksum)?;
+
+        Ok((conf, warnings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ScanDirection;
+
+    use super::*;
+
+    /// Configuration appended twice should still be read, but with a warning
+    #[test]
+    fn warns_about_multiple_magic() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 8]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([6; 8]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(4);
+
+        let mut buf = Cursor::new(&input_data);
+        let (conf, warnings) = options.read_lenient(&mut buf).unwrap();
+        assert_eq!(conf, [6; 8]);
+        assert_eq!(warnings, [Warning::MultipleMagic { count: 2 }]);
+
+        options.scan_direction(ScanDirection::Forward);
+        let (conf, warnings) = options.read_lenient(&mut buf).unwrap();
+        assert_eq!(conf, [5; 8]);
+        assert_eq!(
+            warnings,
+            [
+                Warning::TrailingBytes { len: 12 },
+                Warning::MultipleMagic { count: 2 }
+            ]
+        );
+
+        input_data.truncate(64 + 4 + 8);
+        let mut buf = Cursor::new(&input_data);
+        let (conf, warnings) = options.read_lenient(&mut buf).unwrap();
+        assert_eq!(conf, [5; 8]);
+        assert!(warnings.is_empty());
+    }
+}
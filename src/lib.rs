@@ -24,6 +24,20 @@
 //!
 //! `cat target/debug/binary <(echo -n "CATCONF") conf > confedbinary`
 //!
+//! Or, from a build script or anywhere else a shell isn't practical, with [`ConfWriterOptions`]:
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use catconf::ConfWriterOptions;
+//!
+//! # fn main () -> std::io::Result<()> {
+//! ConfWriterOptions::new(b"CATCONF".to_vec())
+//!     .write_to_path(Path::new("target/debug/binary"), b"conf")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! Great, but how to get the configuration out and use it in the code? catconf!
 //!
 //! It's use is pretty simple:
@@ -39,12 +53,121 @@
 //!
 //! This returns a <code>[Vec]\<u8></code> which can be transformed further, by converting to UTF-8 and
 //! combined with Serde, decompressing with zlib, etc
+//!
+//! # Reproducibility
+//!
+//! The plain format is nothing more than the original binary, the magic bytes, and the
+//! configuration, in that order. The other formats written by [`ConfWriterOptions`] only add
+//! data derived from those: a content type header, a CRC-32 of the payload, a footer holding
+//! the offset of the magic bytes or the length of the configuration, or an index of named
+//! sections. No timestamps, random padding, or other data that could change between runs are
+//! ever added, so appending the same configuration to the same binary with the same options
+//! will always produce byte-identical output; this makes it safe to use in reproducible builds
+//!
+//! # Magic bytes inside the configuration
+//!
+//! With the plain format, the reader can't tell the magic bytes marking the start of the
+//! configuration apart from the same bytes happening to show up inside it, which is likely
+//! enough for compressed or encrypted configuration. If they do, the scan can stop at the copy
+//! inside the configuration and return only the part after it, without any error.
+//!
+//! When the configuration isn't fully under your control, append it with
+//! [`ConfWriterOptions::magic_and_length`] and read it with
+//! [`ConfReaderOptions::magic_and_length`] instead. The length stored at the end of the file
+//! leads straight to the real magic bytes without scanning, and is checked against them, so
+//! copies of the magic bytes inside the configuration are never looked at
+//!
+//! # Packed binaries
+//!
+//! Executable packers such as UPX compress the sections of a binary, but the configuration is
+//! just overlay data at the end of the file which they don't know about. As long as the
+//! configuration is appended *after* packing, reading it works the same as for an unpacked
+//! binary.
+//!
+//! Packing a binary that already has configuration appended is not reliable: UPX keeps the
+//! overlay by default, but will discard it when run with `--overlay=strip`, and other packers
+//! may drop or mangle it without warning. Always pack first, then append the configuration
+//!
+//! # WebAssembly
+//!
+//! On WASI targets such as `wasm32-wasip1` there usually is no executable file to read:
+//! [`std::env::current_exe`] fails, and the module is loaded by the runtime rather than from a
+//! path the guest can see, so [`ConfReaderOptions::read_from_exe`] and [`read_from_exe`] will
+//! return an error. Instead, append the configuration to a file in a directory preopened for
+//! the module and read it with [`ConfReaderOptions::read_from_path`], or pass an already opened
+//! file to [`ConfReaderOptions::read`]. Everything other than finding the current executable is
+//! plain [`Read`] and [`Seek`] and works the same on every target
 
 use std::{
     env,
     fs::OpenOptions,
     io::{self, prelude::*, SeekFrom},
+    ops::Range,
+    path::Path,
+    str, thread,
+    time::{Duration, Instant},
+};
+
+mod aligned;
+#[cfg(feature = "tokio")]
+mod async_read;
+mod checksum;
+mod concat;
+mod content_type;
+mod detect;
+mod error;
+mod extract;
+mod framing;
+#[cfg(feature = "gzip")]
+mod gzip;
+#[cfg(feature = "sha2")]
+mod integrity;
+#[cfg(feature = "json")]
+mod json;
+mod lenient;
+mod magic;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod original;
+mod scan;
+mod sections;
+mod stream;
+#[cfg(feature = "bytemuck")]
+mod structs;
+#[cfg(test)]
+mod test_util;
+mod text;
+mod validate;
+mod writer;
+
+pub use concat::ConcatReadSeek;
+pub use content_type::ContentType;
+pub use error::CatConfError;
+pub use extract::ExtractCursor;
+pub use framing::{Framing, LengthEncoding};
+pub use lenient::Warning;
+pub use magic::{validate_magic, DEFAULT_MAGIC};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapSlice;
+pub use original::OriginalBinaryReader;
+pub use scan::ScanDirection;
+pub use stream::ConfReader;
+pub use validate::{
+    validate_confed_binary, ValidationError, ValidationExpectations, ValidationReport,
 };
+pub use writer::ConfWriterOptions;
+
+use aligned::AlignedReader;
+use checksum::{ChecksumMode, CHECKSUM_LEN};
+use scan::{locate_payload, locate_payloads_backward, read_outermost_payload, Scan};
+
+/// Appended to the path of a binary to get the path of its detached configuration
+const DETACHED_EXTENSION: &str = ".catconf";
+
+/// Amount of each payload read at a time by [`ConfReaderOptions::configs_equal`]
+const COMPARE_CHUNK_SIZE: usize = 8192;
 
 /// Internal function used to just reference the current executable
 pub(crate) fn open_current_exe() -> io::Result<std::fs::File> {
@@ -56,7 +179,7 @@ pub(crate) fn open_current_exe() -> io::Result<std::fs::File> {
 ///
 /// 1. Magic bytes: the bytes used to
 /// 2. Window size: the size of the window used to scan the file. This library
-///     will read in twice the window size to fill its internal buffer
+///    will read in twice the window size to fill its internal buffer
 ///
 /// # Example
 ///
@@ -68,9 +191,28 @@ pub(crate) fn open_current_exe() -> io::Result<std::fs::File> {
 /// # Ok(())
 /// # }
 /// ```
+#[derive(Clone)]
 pub struct ConfReaderOptions {
     magic_bytes_opt: Vec<u8>,
     window_size_opt: i64,
+    retry_attempts_opt: usize,
+    retry_backoff_opt: Duration,
+    require_utf8_opt: bool,
+    framing_opt: Framing,
+    align_to_opt: Option<u64>,
+    aligned_magic_opt: u64,
+    length_range_opt: Option<Range<u64>>,
+    min_read_size_opt: usize,
+    scan_direction_opt: ScanDirection,
+    match_index_opt: usize,
+    length_encoding_opt: LengthEncoding,
+    strip_bom_opt: bool,
+    max_matches_opt: usize,
+    normalize_newlines_opt: bool,
+    deadline_opt: Option<Instant>,
+    skip_prefix_opt: u64,
+    debug_assertions_opt: bool,
+    checksum_opt: ChecksumMode,
 }
 
 impl ConfReaderOptions {
@@ -93,6 +235,24 @@ impl ConfReaderOptions {
         ConfReaderOptions {
             magic_bytes_opt: bytes,
             window_size_opt: 2048,
+            retry_attempts_opt: 1,
+            retry_backoff_opt: Duration::ZERO,
+            require_utf8_opt: false,
+            framing_opt: Framing::Suffix,
+            align_to_opt: None,
+            aligned_magic_opt: 1,
+            length_range_opt: None,
+            min_read_size_opt: 0,
+            scan_direction_opt: ScanDirection::Backward,
+            match_index_opt: 0,
+            length_encoding_opt: LengthEncoding::U64,
+            strip_bom_opt: false,
+            max_matches_opt: usize::MAX,
+            normalize_newlines_opt: false,
+            deadline_opt: None,
+            skip_prefix_opt: 0,
+            debug_assertions_opt: false,
+            checksum_opt: ChecksumMode::Absent,
         }
     }
 
@@ -117,6 +277,9 @@ impl ConfReaderOptions {
 
     /// Sets the window size, influencing the amount of reads that are performed on disk
     ///
+    /// Window sizes smaller than the magic bytes, including 0, are raised to the length of the
+    /// magic bytes, as a smaller window could never find them
+    ///
     /// # Example
     ///
     /// ```
@@ -134,43 +297,58 @@ impl ConfReaderOptions {
         self
     }
 
-    /// Takes the configuration options provided and actually reads from the input file to
-    /// gather the configuration
+    /// Allows for retrying the whole scan when a transient IO error occurs, such as when reading
+    /// from a network backed file. The scan will be attempted at most `attempts` times, waiting
+    /// for `backoff` between each attempt
+    ///
+    /// Only errors that are likely to go away are retried ([`io::ErrorKind::Interrupted`],
+    /// [`io::ErrorKind::TimedOut`], and [`io::ErrorKind::WouldBlock`]); everything else is
+    /// returned immediately
     ///
     /// # Example
     ///
     /// ```
     /// use catconf::ConfReaderOptions;
+    /// use std::time::Duration;
     ///
     /// # fn main() -> std::io::Result<()> {
-    /// # let buff = vec![0;4096];
-    /// # let mut input = std::io::Cursor::new(&buff);
     /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
-    ///     .read(&mut input);
+    ///     .retry_scan(3, Duration::from_millis(100))
+    ///     .read_from_exe()?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn read<F>(&self, input: &mut F) -> io::Result<Vec<u8>>
-    where
-        F: Seek + Read,
-    {
-        read_from_file(&self.magic_bytes_opt, self.window_size_opt, input)
+    pub fn retry_scan(&mut self, attempts: usize, backoff: Duration) -> &mut Self {
+        self.retry_attempts_opt = attempts.max(1);
+        self.retry_backoff_opt = backoff;
+        self
     }
 
-    /// Helper method to go along with [`ConfReaderOptions::read`] in order to read from the
-    /// program currently checking for configuration
+    /// Requires the payload to be valid UTF-8, for configuration that is known to be text.
+    /// The payload is still returned as bytes, but [`ConfReaderOptions::read`] will fail with
+    /// [`CatConfError::Utf8`] instead of returning corrupted text
     ///
-    /// Functionally equivalent to:
+    /// # Example
     ///
     /// ```
     /// use catconf::ConfReaderOptions;
     ///
     /// # fn main() -> std::io::Result<()> {
-    /// let mut current_exe = std::fs::OpenOptions::new().read(true).open(std::env::current_exe()?)?;
-    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read(&mut current_exe)?;
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .require_utf8(true)
+    ///     .read_from_exe();
     /// # Ok(())
     /// # }
     /// ```
+    pub fn require_utf8(&mut self, require: bool) -> &mut Self {
+        self.require_utf8_opt = require;
+        self
+    }
+
+    /// Removes a UTF-8 byte order mark (`EF BB BF`) from the start of the payload, which some
+    /// Windows tools write at the start of text files and which most parsers, including
+    /// serde_json, reject. Any checksum still covers the payload as written, with the byte
+    /// order mark
     ///
     /// # Example
     ///
@@ -178,119 +356,2609 @@ impl ConfReaderOptions {
     /// use catconf::ConfReaderOptions;
     ///
     /// # fn main() -> std::io::Result<()> {
-    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_from_exe()?;
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF\xef\xbb\xbf{}");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .strip_bom(true)
+    ///     .read(&mut input)?;
+    /// # assert_eq!(conf, b"{}");
     /// # Ok(())
     /// # }
     /// ```
-    pub fn read_from_exe(&self) -> io::Result<Vec<u8>> {
-        let mut cur_exe = open_current_exe()?;
-        self.read(&mut cur_exe)
+    pub fn strip_bom(&mut self, strip: bool) -> &mut Self {
+        self.strip_bom_opt = strip;
+        self
     }
-}
 
-/// Useful if you just want to read from the current exe without bothering to use the builder
-///
-/// # Example
-///
-/// ```
-/// use catconf::read_from_exe;
-///
-/// # fn main() -> std::io::Result<()> {
-/// let conf = read_from_exe(b"CATCONF", 4096)?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn read_from_exe(magic_bytes: &[u8], window_size: i64) -> io::Result<Vec<u8>> {
-    let mut cur_exe = open_current_exe()?;
-    read_from_file(magic_bytes, window_size, &mut cur_exe)
-}
+    /// Converts Windows (`\r\n`) and old Mac (`\r`) line endings to `\n` when reading the
+    /// configuration as text with [`ConfReaderOptions::read_string`], so that configuration
+    /// appended on different platforms is parsed the same. Methods returning the payload as
+    /// bytes are left alone
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFa=1\r\nb=2\r\n");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .normalize_newlines(true)
+    ///     .read_string(&mut input)?;
+    /// # assert_eq!(conf, "a=1\nb=2\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalize_newlines(&mut self, normalize: bool) -> &mut Self {
+        self.normalize_newlines_opt = normalize;
+        self
+    }
 
-/// Allows for reading for configuration from the end of a file by looking for magic bytes
-///
-/// # Example
-///
-/// ```no_run
-/// use catconf::read_from_file;
-///
-/// # fn main() -> std::io::Result<()> {
-/// # let buff = vec![0; 4096];
-/// # let mut input = std::io::Cursor::new(&buff);
-/// let conf = read_from_file(b"CATCONF", 2048, &mut input)?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn read_from_file<F>(magic_bytes: &[u8], window_size: i64, input: &mut F) -> io::Result<Vec<u8>>
-where
-    F: Seek + Read,
-{
-    let buffer_size = window_size * 2;
-    let mut current_window_index: i64 = 1;
-    let mut current_read_buffer = vec![0u8; buffer_size as usize];
-
-    loop {
-        input.seek(SeekFrom::End(-((current_window_index + 1) * window_size)))?;
-        let bytes_read = input.read(&mut current_read_buffer[..])?;
-
-        if bytes_read < window_size as usize {
-            break Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "reached beginning of the file without finding magic bytes",
-            ));
-        }
+    /// Expects the file to end with an offset pointer footer, as written by
+    /// [`ConfWriterOptions::offset_pointer_footer`]. The last 8 bytes of the file are then a
+    /// little endian `u64` giving the offset of the magic bytes, allowing the configuration to
+    /// be found without scanning the file:
+    ///
+    /// `binary || magic bytes || configuration || offset of magic bytes`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .offset_pointer_footer(true)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .offset_pointer_footer(true)
+    ///     .read(&mut file)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn offset_pointer_footer(&mut self, enabled: bool) -> &mut Self {
+        self.framing_opt = if enabled {
+            Framing::OffsetPointer
+        } else {
+            Framing::Suffix
+        };
+        self
+    }
 
-        if let Some(pos) = current_read_buffer
-            .windows(magic_bytes.len())
-            .position(|window| window == magic_bytes)
-        {
-            let conf_buffer_size = window_size - pos as i64 - magic_bytes.len() as i64
-                + (current_window_index * window_size);
-            let mut conf_buffer = vec![0; conf_buffer_size as usize];
+    /// Makes every read from the input file start at a multiple of the block size and be a
+    /// multiple of the block size long, for raw block devices which reject any other reads.
+    /// The relevant bytes are sliced out of the blocks read
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut partition = std::fs::File::open("/dev/sdb2")?;
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .align_to(512)
+    ///     .read(&mut partition)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn align_to(&mut self, block_size: u64) -> &mut Self {
+        self.align_to_opt = Some(block_size).filter(|size| *size > 1);
+        self
+    }
 
-            input.seek(SeekFrom::End(-conf_buffer_size))?;
-            input.read(&mut conf_buffer[..])?;
+    /// Enables extra sanity checks on where the payload was found, such as making sure it is
+    /// within the bounds of the file, returning a descriptive error instead of continuing with
+    /// an inconsistent layout. Useful for debugging custom input files, but requires extra
+    /// seeks and so is off by default
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .debug_assertions(cfg!(debug_assertions))
+    ///     .read_from_exe()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn debug_assertions(&mut self, enabled: bool) -> &mut Self {
+        self.debug_assertions_opt = enabled;
+        self
+    }
 
-            break Ok(conf_buffer);
-        }
+    /// Expects a CRC-32 of the configuration after it, as written by
+    /// [`ConfWriterOptions::checksum`], and checks it when the configuration is read with
+    /// [`ConfReaderOptions::read`] or [`ConfReaderOptions::read_slice`]. A mismatch results in
+    /// [`CatConfError::ChecksumMismatch`]
+    ///
+    /// The checksum is never included in the configuration returned by any of the methods
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .checksum(true)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .checksum(true)
+    ///     .read(&mut file)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checksum(&mut self, validate: bool) -> &mut Self {
+        self.checksum_opt = if validate {
+            ChecksumMode::Validate
+        } else {
+            ChecksumMode::Absent
+        };
+        self
+    }
 
-        current_window_index += 1;
+    /// Expects a CRC-32 after the configuration like [`ConfReaderOptions::checksum`], but
+    /// doesn't check it, for trusted paths where computing the checksum isn't worth it. The
+    /// checksum is still left out of the configuration returned
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .checksum_present_skip_validation(true)
+    ///     .read_from_exe();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checksum_present_skip_validation(&mut self, skip: bool) -> &mut Self {
+        self.checksum_opt = if skip {
+            ChecksumMode::SkipValidation
+        } else {
+            ChecksumMode::Absent
+        };
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::io::Cursor;
+    /// Expects the configuration to be followed by its length, as written by
+    /// [`ConfWriterOptions::magic_and_length`]. The last 8 bytes of the file are then a little
+    /// endian `u64` giving the length of the configuration, which is used to find the magic
+    /// bytes without scanning the file:
+    ///
+    /// `binary || magic bytes || configuration || length of configuration`
+    ///
+    /// Unlike the offset pointer footer, the magic bytes are still checked for in front of the
+    /// configuration, so a corrupted length is caught instead of returning the wrong bytes.
+    /// This is the most robust of the framings, and recommended when the configuration is
+    /// written with [`ConfWriterOptions`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .read(&mut file)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn magic_and_length(&mut self, enabled: bool) -> &mut Self {
+        self.framing_opt = if enabled {
+            Framing::MagicAndLength
+        } else {
+            Framing::Suffix
+        };
+        self
+    }
 
-    use super::*;
+    /// Expects the configuration to be at the front of the file instead of appended to the
+    /// end, directly after a stub of [`ConfReaderOptions::skip_prefix`] bytes, such as a
+    /// self-extracting stub which reads forward through the file. The stub is followed by a
+    /// little endian `u32` giving the length of the configuration, and then exactly that many
+    /// bytes of configuration:
+    ///
+    /// `stub || length of configuration || configuration || anything else`
+    ///
+    /// There are no magic bytes to check, so the length is trusted as long as it fits in the
+    /// file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 256];
+    /// # buff.extend(4u32.to_le_bytes());
+    /// # buff.extend(b"confarchive");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(vec![])
+    ///     .prefix_length_framed(true)
+    ///     .skip_prefix(256)
+    ///     .read(&mut input)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prefix_length_framed(&mut self, enabled: bool) -> &mut Self {
+        self.framing_opt = if enabled {
+            Framing::LengthPrefix
+        } else {
+            Framing::Suffix
+        };
+        self
+    }
 
-    /// Simplest use case
-    #[test]
-    fn pulls_basic_data() {
-        let input_data = [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-            1, 1, 1,
-        ];
-        let header = [1, 2, 3, 4];
-        let data = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+    /// Sets how many bytes of stub at the start of the file come before the length read by
+    /// [`ConfReaderOptions::prefix_length_framed`]. Defaults to 0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// let mut options = ConfReaderOptions::new(vec![]);
+    /// options.prefix_length_framed(true).skip_prefix(4096);
+    /// ```
+    pub fn skip_prefix(&mut self, len: u64) -> &mut Self {
+        self.skip_prefix_opt = len;
+        self
+    }
 
-        let mut buf = Cursor::new(&input_data);
+    /// Sets how the length read by [`ConfReaderOptions::magic_and_length`] is encoded, which
+    /// has to match what it was written with by [`ConfWriterOptions::length_encoding`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions, LengthEncoding};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .length_encoding(LengthEncoding::Varint)
+    ///     .write(&mut file, b"conf")?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .length_encoding(LengthEncoding::Varint)
+    ///     .read(&mut file)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn length_encoding(&mut self, encoding: LengthEncoding) -> &mut Self {
+        self.length_encoding_opt = encoding;
+        self
+    }
 
-        assert_eq!(&read_from_file(&header, 16, &mut buf).unwrap(), &data);
+    /// Only looks for the magic bytes at offsets in the file which are a multiple of the block
+    /// size given, for when the configuration is known to be appended at a block boundary (for
+    /// instance by padding the binary first). This cuts down on the comparisons done while
+    /// scanning, and means copies of the magic bytes at other offsets are never matched
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .aligned_magic(4096)
+    ///     .read(&mut input)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn aligned_magic(&mut self, block_size: usize) -> &mut Self {
+        self.aligned_magic_opt = block_size.max(1) as u64;
+        self
     }
 
-    /// Check to make sure reads occur when going across window boundaries. For instance, in
+    /// Sets which end of the file to start scanning for the magic bytes from. Scanning
+    /// backward, the default, finds the outermost (last appended) block of configuration
+    /// first; scanning forward finds the innermost (first appended) block first, which is
+    /// faster when that is the one wanted and the blocks after it are large
+    ///
+    /// Either way, the payload found stops at the magic bytes of the next block, if any. Any
+    /// copy of the magic bytes in the original binary is found first when scanning forward
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ScanDirection};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFbase");
+    /// # buff.extend(b"CATCONFoverride");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let base = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .scan_direction(ScanDirection::Forward)
+    ///     .read(&mut input)?;
+    /// # assert_eq!(base, b"base");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scan_direction(&mut self, direction: ScanDirection) -> &mut Self {
+        self.scan_direction_opt = direction;
+        self
+    }
+
+    /// Skips over this many blocks of configuration in the direction of the scan (see
+    /// [`ConfReaderOptions::scan_direction`]) before reading one, so that 0 is the first block
+    /// found, 1 is the one after that, and so on. Only applies when scanning for the magic
+    /// bytes rather than using a footer to find them
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFbase");
+    /// # buff.extend(b"CATCONFoverride");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let base = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .match_index(1)
+    ///     .read(&mut input)?;
+    /// # assert_eq!(base, b"base");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn match_index(&mut self, index: usize) -> &mut Self {
+        self.match_index_opt = index;
+        self
+    }
+
+    /// Limits how many copies of the magic bytes are collected by the methods that look for
+    /// all of them, such as [`ConfReaderOptions::read_blocks`], so that a file crafted to be
+    /// full of the magic bytes can't use up unbounded memory. By default there is no limit
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFbase");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let blocks = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .max_matches(16)
+    ///     .read_blocks(&mut input)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_matches(&mut self, max: usize) -> &mut Self {
+        self.max_matches_opt = max;
+        self
+    }
+
+    /// Sets the minimum amount of bytes read from the file at a time while scanning for the
+    /// magic bytes. Each read is normally twice the window size, which can mean a lot of
+    /// reads for small windows on storage where every read is slow; if a read would be smaller
+    /// than this, the window is grown until it isn't
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .window_size(512)
+    ///     .min_read_size(1024 * 1024)
+    ///     .read_from_exe()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn min_read_size(&mut self, size: usize) -> &mut Self {
+        self.min_read_size_opt = size;
+        self
+    }
+
+    /// Sets the range of lengths the payload is expected to have. If the payload found is
+    /// shorter or longer, reading fails with [`CatConfError::LengthOutOfRange`], which is a cheap
+    /// way to catch configuration that was truncated or appended more than once
+    ///
+    /// The length checked does not include a checksum stored with the payload
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .expected_length_range(16..4096)
+    ///     .read_from_exe();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expected_length_range(&mut self, range: Range<u64>) -> &mut Self {
+        self.length_range_opt = Some(range);
+        self
+    }
+
+    /// Takes the configuration options provided and actually reads from the input file to
+    /// gather the configuration
+    ///
+    /// # Truncated configuration
+    ///
+    /// **With the default plain suffix framing, configuration which itself contains the magic
+    /// bytes is silently cut short:** the scan can't tell the copy inside the configuration
+    /// apart from the real one, and returns only the bytes after it without any error. This
+    /// is likely for compressed or encrypted configuration. [`ConfWriterOptions::write`]
+    /// refuses to write such configuration, but files appended with `cat` aren't checked. Use
+    /// [`ConfReaderOptions::magic_and_length`] for configuration that may contain the magic
+    /// bytes; see [Magic bytes inside the configuration](crate#magic-bytes-inside-the-configuration)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let buff = vec![0;4096];
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read(&mut input);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read<F>(&self, input: &mut F) -> io::Result<Vec<u8>>
+    where
+        F: Seek + Read,
+    {
+        let (conf, checksum) = self.with_input(input, |input| self.read_once(input))?;
+
+        self.finish_payload(conf, checksum)
+    }
+
+    /// Reads the configuration the same as [`ConfReaderOptions::read`], but gives back `None`
+    /// instead of an error when the magic bytes aren't found anywhere in the file, so that a
+    /// binary without any configuration appended can fall back to defaults while real IO
+    /// errors are still returned
+    ///
+    /// The same case is reported by the other methods as [`CatConfError::NotFound`], carried
+    /// in an error of kind [`io::ErrorKind::UnexpectedEof`]. This only applies when scanning
+    /// for the magic bytes; with the footer framings, a file without configuration has no
+    /// footer, so whatever is at the end of the file doesn't lead to the magic bytes and gives
+    /// an [`io::ErrorKind::InvalidData`] error instead
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let buff = vec![0; 4096];
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = match ConfReaderOptions::new(b"CATCONF".to_vec()).read_optional(&mut input)? {
+    ///     Some(conf) => conf,
+    ///     None => b"defaults".to_vec(),
+    /// };
+    /// # assert_eq!(conf, b"defaults");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_optional<F>(&self, input: &mut F) -> io::Result<Option<Vec<u8>>>
+    where
+        F: Seek + Read,
+    {
+        match self.read(input) {
+            Ok(conf) => Ok(Some(conf)),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads the configuration the same as [`ConfReaderOptions::read`], but gives up with
+    /// [`CatConfError::DeadlineExceeded`] once the deadline has passed, for startup paths
+    /// which can't wait on slow media. The clock is checked before each window of the scan for
+    /// the magic bytes is read, so a single slow read can still run past the deadline
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let deadline = Instant::now() + Duration::from_millis(50);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_with_deadline(&mut input, deadline)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_with_deadline<F>(
+        &self,
+        input: &mut F,
+        deadline: Instant,
+    ) -> Result<Vec<u8>, CatConfError>
+    where
+        F: Seek + Read,
+    {
+        let mut options = self.clone();
+        options.deadline_opt = Some(deadline);
+
+        Ok(options.read(input)?)
+    }
+
+    /// Reads configuration in a framing defined by the application, which is parsed by the
+    /// callback given. The callback is passed the input file and returns the offset and length
+    /// of the payload, typically by seeking to and decoding a header or footer of its own; the
+    /// payload is then checked to be within the file and read the same as with
+    /// [`ConfReaderOptions::read`]
+    ///
+    /// The magic bytes, window size, framing, and checksum options are not used, as finding
+    /// the payload is left to the callback
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::{Read, Seek, SeekFrom};
+    ///
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"conf");
+    /// # buff.extend(4u16.to_be_bytes());
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// // The payload is followed by its length as a big endian u16
+    /// let conf = ConfReaderOptions::new(vec![]).read_custom_framing(&mut input, |input| {
+    ///     let footer_start = input.seek(SeekFrom::End(-2))?;
+    ///     let mut len = [0; 2];
+    ///     input.read_exact(&mut len)?;
+    ///     let len = u64::from(u16::from_be_bytes(len));
+    ///
+    ///     Ok((footer_start - len, len))
+    /// })?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_custom_framing<F, P>(
+        &self,
+        input: &mut F,
+        mut parse_header: P,
+    ) -> io::Result<Vec<u8>>
+    where
+        F: Seek + Read,
+        P: FnMut(&mut dyn ReadSeek) -> io::Result<(u64, u64)>,
+    {
+        let conf = self.with_input(input, |input| {
+            let (offset, len) = parse_header(input)?;
+            let location = PayloadLocation {
+                magic_offset: offset,
+                offset,
+                len,
+            };
+
+            check_location(&[], input, location)?;
+            self.check_length(len)?;
+            read_payload(input, location)
+        })?;
+
+        self.finish_payload(conf, None)
+    }
+
+    /// Reads the configuration the same as [`ConfReaderOptions::read`], but also returns the
+    /// total length of the file, such as for showing how much of it is configuration, without
+    /// needing a separate call to get the file's metadata
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let (conf, file_len) = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_with_file_len(&mut input)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # assert_eq!(file_len, 4107);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_with_file_len<F>(&self, input: &mut F) -> io::Result<(Vec<u8>, u64)>
+    where
+        F: Seek + Read,
+    {
+        let (conf, checksum, file_len) = self.with_input(input, |input| {
+            let (conf, checksum) = self.read_once(input)?;
+            let file_len = input.seek(SeekFrom::End(0))?;
+
+            Ok((conf, checksum, file_len))
+        })?;
+
+        Ok((self.finish_payload(conf, checksum)?, file_len))
+    }
+
+    /// Finds the configuration in a buffer that is already in memory, honoring the same options
+    /// as [`ConfReaderOptions::read`]. The payload is returned as a slice of the buffer rather
+    /// than being copied
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut data = vec![0; 4096];
+    /// # data.extend(b"CATCONFconf");
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_slice(&data)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_slice<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], CatConfError> {
+        let location =
+            self.with_input(&mut io::Cursor::new(data), |input| self.locate_once(input))?;
+        let end = (location.offset + location.len) as usize;
+        let conf = &data[location.offset as usize..end];
+
+        let checksum = match self.checksum_opt {
+            ChecksumMode::Validate => Some(framing::read_u32(&mut &data[end..])?),
+            _ => None,
+        };
+
+        self.check_payload(conf, checksum)?;
+
+        if self.strip_bom_opt {
+            return Ok(conf.strip_prefix(text::UTF8_BOM).unwrap_or(conf));
+        }
+
+        Ok(conf)
+    }
+
+    /// Locates the configuration in the input file, but only reads the bytes of the payload
+    /// that fall within the range provided. Useful for large payloads where only a header or
+    /// similar small section is needed
+    ///
+    /// The range is relative to the start of the payload, and must fit within the payload
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend([1; 64]);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let header = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_payload_range(&mut input, 0..16)?;
+    /// # assert_eq!(header, [1; 16]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_payload_range<F>(&self, input: &mut F, range: Range<u64>) -> io::Result<Vec<u8>>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            let location = self.locate_once(input)?;
+
+            if range.start > range.end || range.end > location.len {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "range {}..{} is out of bounds for payload of length {}",
+                        range.start, range.end, location.len
+                    ),
+                ));
+            }
+
+            input.seek(SeekFrom::Start(location.offset + range.start))?;
+            read_exact_vec(input, range.end - range.start)
+        })
+    }
+
+    /// Locates the configuration in the input file and returns how many bytes after the magic
+    /// bytes there are until the end of the file. For a plain configuration this is just the
+    /// length of the payload; anything beyond that indicates trailing data such as a signature
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend([1; 64]);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let distance = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .payload_distance_from_end(&mut input)?;
+    /// # assert_eq!(distance, 64);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn payload_distance_from_end<F>(&self, input: &mut F) -> io::Result<u64>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            let location = self.locate_once(input)?;
+            let file_len = input.seek(SeekFrom::End(0))?;
+
+            Ok(file_len - location.offset)
+        })
+    }
+
+    /// Locates the configuration in the input file and returns the offset of the magic bytes,
+    /// which is also the length of the original binary before the configuration was appended
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend([1; 64]);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let boundary = ConfReaderOptions::new(b"CATCONF".to_vec()).config_boundary(&mut input)?;
+    /// # assert_eq!(boundary, 4096);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn config_boundary<F>(&self, input: &mut F) -> io::Result<u64>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| Ok(self.locate_once(input)?.magic_offset))
+    }
+
+    /// Streaming counterpart to [`ConfReaderOptions::config_boundary`], which wraps the input
+    /// file and only provides the bytes of the original binary, stopping right before the
+    /// magic bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    /// use std::io::Read;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend([1; 64]);
+    /// # let input = std::io::Cursor::new(&buff);
+    /// let mut original = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .original_binary_reader(input)?;
+    ///
+    /// let mut binary = vec![];
+    /// original.read_to_end(&mut binary)?;
+    /// # assert_eq!(binary, vec![0; 4096]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn original_binary_reader<F>(&self, mut input: F) -> io::Result<OriginalBinaryReader<F>>
+    where
+        F: Seek + Read,
+    {
+        let boundary = self.config_boundary(&mut input)?;
+        input.seek(SeekFrom::Start(0))?;
+
+        Ok(OriginalBinaryReader::new(input, boundary))
+    }
+
+    /// Locates the configuration and returns a reader which is positioned at the start of it
+    /// and stops at the end of it, so that a large payload can be streamed into a decoder or
+    /// another file without being held in memory. Reading it to the end gives the same bytes
+    /// as [`ConfReaderOptions::read`]
+    ///
+    /// Locating the payload honors all of the options, but as the payload is never read as a
+    /// whole, any checksum is skipped without being validated, and the UTF-8, BOM, and
+    /// alignment options don't apply to reading from the stream
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    /// use std::io::Read;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let input = std::io::Cursor::new(&buff);
+    /// let mut reader = ConfReaderOptions::new(b"CATCONF".to_vec()).read_streaming(input)?;
+    /// println!("config is {} bytes", reader.len());
+    ///
+    /// let mut conf = vec![];
+    /// reader.read_to_end(&mut conf)?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_streaming<F>(&self, mut input: F) -> io::Result<ConfReader<F>>
+    where
+        F: Seek + Read,
+    {
+        let location = self.with_input(&mut input, |input| self.locate_once(input))?;
+        input.seek(SeekFrom::Start(location.offset))?;
+
+        Ok(ConfReader::new(input, location.len))
+    }
+
+    /// Helper method to go along with [`ConfReaderOptions::read_streaming`] in order to stream
+    /// the configuration of the program currently running
+    ///
+    /// Not supported on WASI, see [`ConfReaderOptions::read_from_path`] instead
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut reader = ConfReaderOptions::new(b"CATCONF".to_vec()).read_streaming_from_exe()?;
+    /// let mut dest = std::fs::File::create("conf.sqlite")?;
+    /// std::io::copy(&mut reader, &mut dest)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_streaming_from_exe(&self) -> io::Result<ConfReader<std::fs::File>> {
+        self.read_streaming(open_current_exe()?)
+    }
+
+    /// Reads every block of configuration in the file, for when configuration has been
+    /// appended more than once. Blocks are returned from the outermost (last appended) to the
+    /// innermost (first appended), and each one stops at the magic bytes of the block after it
+    ///
+    /// This always scans the whole file for the magic bytes, so any copy of the magic bytes
+    /// in the original binary will show up as the innermost block. If
+    /// [`ConfReaderOptions::max_matches`] is set, only that many of the outermost blocks are
+    /// returned; use [`ConfReaderOptions::read_blocks_capped`] to find out if any were left out
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFbase");
+    /// # buff.extend(b"CATCONFoverride");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let blocks = ConfReaderOptions::new(b"CATCONF".to_vec()).read_blocks(&mut input)?;
+    /// # assert_eq!(blocks, [b"override".to_vec(), b"base".to_vec()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_blocks<F>(&self, input: &mut F) -> io::Result<Vec<Vec<u8>>>
+    where
+        F: Seek + Read,
+    {
+        Ok(self.read_blocks_capped(input)?.0)
+    }
+
+    /// Same as [`ConfReaderOptions::read_blocks`], but also returns whether there were more
+    /// blocks than [`ConfReaderOptions::max_matches`] allows, in which case the innermost
+    /// blocks were left out
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFbase");
+    /// # buff.extend(b"CATCONFoverride");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let (blocks, truncated) = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .max_matches(1)
+    ///     .read_blocks_capped(&mut input)?;
+    /// # assert_eq!(blocks, [b"override".to_vec()]);
+    /// # assert!(truncated);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_blocks_capped<F>(&self, input: &mut F) -> io::Result<(Vec<Vec<u8>>, bool)>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            // Look for one more block than allowed, to tell if there were too many
+            let limit = self.max_matches_opt.saturating_add(1);
+            let mut locations = locate_payloads_backward(&self.scan(), input, limit)?;
+            let truncated = locations.len() > self.max_matches_opt;
+            locations.truncate(self.max_matches_opt);
+
+            let blocks = locations
+                .into_iter()
+                .map(|location| read_payload(input, location))
+                .collect::<io::Result<_>>()?;
+
+            Ok((blocks, truncated))
+        })
+    }
+
+    /// Locates the configuration in both files and checks whether they are byte for byte the
+    /// same, such as to confirm two builds were deployed with the same configuration. The
+    /// payloads are compared a chunk at a time rather than reading either fully into memory
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut a = vec![0; 4096];
+    /// # a.extend(b"CATCONFconf");
+    /// # let mut b = vec![1; 2048];
+    /// # b.extend(b"CATCONFconf");
+    /// # let mut a = std::io::Cursor::new(&a);
+    /// # let mut b = std::io::Cursor::new(&b);
+    /// let equal = ConfReaderOptions::new(b"CATCONF".to_vec()).configs_equal(&mut a, &mut b)?;
+    /// # assert!(equal);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn configs_equal<F1, F2>(&self, a: &mut F1, b: &mut F2) -> io::Result<bool>
+    where
+        F1: Seek + Read,
+        F2: Seek + Read,
+    {
+        self.with_input(a, |a| {
+            self.with_input(b, |b| {
+                let location_a = self.locate_once(a)?;
+                let location_b = self.locate_once(b)?;
+
+                if location_a.len != location_b.len {
+                    return Ok(false);
+                }
+
+                a.seek(SeekFrom::Start(location_a.offset))?;
+                b.seek(SeekFrom::Start(location_b.offset))?;
+
+                let mut chunk_a = vec![0; COMPARE_CHUNK_SIZE];
+                let mut chunk_b = vec![0; COMPARE_CHUNK_SIZE];
+                let mut remaining = location_a.len;
+
+                while remaining > 0 {
+                    let len = remaining.min(COMPARE_CHUNK_SIZE as u64) as usize;
+                    a.read_exact(&mut chunk_a[..len])?;
+                    b.read_exact(&mut chunk_b[..len])?;
+
+                    if chunk_a[..len] != chunk_b[..len] {
+                        return Ok(false);
+                    }
+
+                    remaining -= len as u64;
+                }
+
+                Ok(true)
+            })
+        })
+    }
+
+    /// Estimates the worst case IO needed to find the configuration in a file of the given
+    /// length, without touching the file. The worst case is a file without any configuration,
+    /// where every window has to be scanned; reading the payload itself isn't included
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// let estimate = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .window_size(4096)
+    ///     .estimate_scan_cost(64 * 1024 * 1024);
+    ///
+    /// println!("{} reads, {} bytes", estimate.max_windows, estimate.max_bytes_read);
+    /// ```
+    pub fn estimate_scan_cost(&self, file_len: u64) -> ScanCostEstimate {
+        match self.framing_opt {
+            Framing::Suffix => {
+                let window_size = self.scan().window_size as u64;
+                let buffer_size = window_size * 2;
+                let max_windows = file_len.saturating_sub(buffer_size).div_ceil(window_size) + 1;
+
+                ScanCostEstimate {
+                    max_windows,
+                    max_bytes_read: (max_windows - 1) * buffer_size + file_len.min(buffer_size),
+                }
+            }
+            Framing::OffsetPointer => ScanCostEstimate {
+                max_windows: 0,
+                max_bytes_read: framing::FOOTER_LEN + self.magic_bytes_opt.len() as u64,
+            },
+            Framing::MagicAndLength => ScanCostEstimate {
+                max_windows: 0,
+                max_bytes_read: self.length_encoding_opt.max_len()
+                    + self.magic_bytes_opt.len() as u64,
+            },
+            Framing::LengthPrefix => ScanCostEstimate {
+                max_windows: 0,
+                max_bytes_read: 4,
+            },
+            Framing::Sections | Framing::Unknown => ScanCostEstimate {
+                max_windows: 0,
+                max_bytes_read: 0,
+            },
+        }
+    }
+
+    /// Helper method to go along with [`ConfReaderOptions::read`] in order to read from the
+    /// program currently checking for configuration
+    ///
+    /// Not supported on WASI, see [`ConfReaderOptions::read_from_path`] instead
+    ///
+    /// Functionally equivalent to:
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut current_exe = std::fs::OpenOptions::new().read(true).open(std::env::current_exe()?)?;
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read(&mut current_exe)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_from_exe()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_from_exe(&self) -> io::Result<Vec<u8>> {
+        let mut cur_exe = open_current_exe()?;
+        self.read(&mut cur_exe)
+    }
+
+    /// Opens the file at the path given and reads the configuration from it
+    ///
+    /// This is the way to read configuration on targets where the current executable can't be
+    /// opened, such as WASI, where the path can be in any directory preopened for the module
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_from_path("/config/app.wasm")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_from_path<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let mut file = OpenOptions::new().read(true).open(path)?;
+        self.read(&mut file)
+    }
+
+    /// Reads configuration kept in a file next to the binary rather than appended to it, for
+    /// when the binary can't be modified. The configuration is the entire contents of
+    /// `<binary>.catconf`, without any magic bytes or framing; only the checks on the contents
+    /// such as [`ConfReaderOptions::require_utf8`] apply
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// // Reads /usr/bin/app.catconf
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_detached(std::path::Path::new("/usr/bin/app"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_detached(&self, binary_path: &Path) -> io::Result<Vec<u8>> {
+        let mut detached_path = binary_path.as_os_str().to_owned();
+        detached_path.push(DETACHED_EXTENSION);
+
+        let conf = std::fs::read(detached_path)?;
+
+        self.check_length(conf.len() as u64)?;
+        self.check_payload(&conf, None)?;
+
+        Ok(conf)
+    }
+
+    /// Reads the configuration appended to the current executable, falling back to the
+    /// detached configuration next to it (see [`ConfReaderOptions::read_detached`]) if there
+    /// isn't any. If neither is found, the error from reading the executable is returned
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_from_exe_or_detached();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_from_exe_or_detached(&self) -> io::Result<Vec<u8>> {
+        let exe_path = env::current_exe()?;
+        let embedded = OpenOptions::new()
+            .read(true)
+            .open(&exe_path)
+            .and_then(|mut exe| self.read(&mut exe));
+
+        match embedded {
+            Ok(conf) => Ok(conf),
+            Err(embedded_err) => match self.read_detached(&exe_path) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Err(embedded_err),
+                detached => detached,
+            },
+        }
+    }
+
+    /// Internal method used to describe how the file should be scanned for the magic bytes
+    fn scan(&self) -> Scan<'_> {
+        Scan {
+            magic_bytes: &self.magic_bytes_opt,
+            window_size: self
+                .window_size_opt
+                .max(self.min_read_size_opt.div_ceil(2) as i64)
+                .max(self.magic_bytes_opt.len().max(1) as i64),
+            magic_alignment: self.aligned_magic_opt,
+            direction: self.scan_direction_opt,
+            match_index: self.match_index_opt,
+            deadline: self.deadline_opt,
+        }
+    }
+
+    /// Internal method used to find the payload according to the configured framing. Should be
+    /// called from within [`ConfReaderOptions::with_input`]
+    fn locate_once<F>(&self, input: &mut F) -> io::Result<PayloadLocation>
+    where
+        F: Seek + Read + ?Sized,
+    {
+        let mut location = match self.framing_opt {
+            Framing::Suffix => locate_payload(&self.scan(), input),
+            Framing::OffsetPointer => framing::locate_offset_pointer(&self.magic_bytes_opt, input),
+            Framing::MagicAndLength => framing::locate_magic_and_length(
+                &self.magic_bytes_opt,
+                self.length_encoding_opt,
+                input,
+            ),
+            Framing::LengthPrefix => framing::locate_length_prefix(self.skip_prefix_opt, input),
+            framing => Err(framing::unsupported_framing(framing)),
+        }?;
+
+        if self.debug_assertions_opt {
+            let magic_bytes = match self.framing_opt {
+                // The length is where the magic bytes would be
+                Framing::LengthPrefix => &[],
+                _ => &self.magic_bytes_opt[..],
+            };
+            check_location(magic_bytes, input, location)?;
+        }
+
+        location.len = self.conf_len(location.len)?;
+
+        Ok(location)
+    }
+
+    /// Internal method used to find the length of the configuration in a payload of the given
+    /// length, leaving out the checksum after it, and make sure it is within the range expected
+    fn conf_len(&self, payload_len: u64) -> io::Result<u64> {
+        let len = match self.checksum_opt {
+            ChecksumMode::Absent => payload_len,
+            _ => payload_len.checked_sub(CHECKSUM_LEN).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "payload is too short to contain a checksum",
+                )
+            })?,
+        };
+
+        self.check_length(len)?;
+
+        Ok(len)
+    }
+
+    /// Internal method used to find and read the payload, along with the checksum stored
+    /// after it if it is to be validated. Should be called from within
+    /// [`ConfReaderOptions::with_input`]
+    fn read_once(&self, input: &mut dyn ReadSeek) -> io::Result<(Vec<u8>, Option<u32>)> {
+        // The usual case of the last payload in the file can be read straight from the scan,
+        // along with any checksum at the end of it
+        if self.framing_opt == Framing::Suffix
+            && self.scan_direction_opt == ScanDirection::Backward
+            && self.match_index_opt == 0
+            && !self.debug_assertions_opt
+        {
+            let mut conf = read_outermost_payload(&self.scan(), input, |location| {
+                self.conf_len(location.len).map(drop)
+            })?;
+            let checksum = match self.checksum_opt {
+                ChecksumMode::Absent => None,
+                mode => {
+                    let end = conf.len() - CHECKSUM_LEN as usize;
+                    let checksum = framing::read_u32(&mut &conf[end..])?;
+                    conf.truncate(end);
+
+                    (mode == ChecksumMode::Validate).then_some(checksum)
+                }
+            };
+
+            return Ok((conf, checksum));
+        }
+
+        let location = self.locate_once(input)?;
+        let conf = read_payload(input, location)?;
+
+        // The checksum immediately follows the payload, where the input is left after
+        // reading it
+        let checksum = match self.checksum_opt {
+            ChecksumMode::Validate => Some(framing::read_u32(input)?),
+            _ => None,
+        };
+
+        Ok((conf, checksum))
+    }
+
+    /// Internal method used to check the payload read by [`ConfReaderOptions::read_once`]
+    /// and apply any changes to it before returning it
+    fn finish_payload(&self, mut conf: Vec<u8>, checksum: Option<u32>) -> io::Result<Vec<u8>> {
+        self.check_payload(&conf, checksum)?;
+
+        if self.strip_bom_opt && conf.starts_with(text::UTF8_BOM) {
+            conf.drain(..text::UTF8_BOM.len());
+        }
+
+        Ok(conf)
+    }
+
+    /// Internal method used to make sure the length of the payload is within the range expected
+    fn check_length(&self, len: u64) -> io::Result<()> {
+        match &self.length_range_opt {
+            Some(range) if !range.contains(&len) => Err(CatConfError::LengthOutOfRange {
+                len,
+                range: range.clone(),
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Internal method used to make sure the payload read meets any requirements set, given
+    /// the checksum stored with it if it is to be validated
+    fn check_payload(&self, conf: &[u8], checksum: Option<u32>) -> io::Result<()> {
+        if let Some(expected) = checksum {
+            let found = checksum::crc32(conf);
+
+            if found != expected {
+                return Err(CatConfError::ChecksumMismatch { expected, found }.into());
+            }
+        }
+
+        if self.require_utf8_opt {
+            str::from_utf8(conf).map_err(CatConfError::Utf8)?;
+        }
+
+        Ok(())
+    }
+
+    /// Internal method used to run an operation against the input, honoring the options that
+    /// affect every access to it such as retries and alignment
+    fn with_input<F, T, O>(&self, input: &mut F, mut operation: O) -> io::Result<T>
+    where
+        F: Seek + Read,
+        O: FnMut(&mut dyn ReadSeek) -> io::Result<T>,
+    {
+        self.with_retries(|| match self.align_to_opt {
+            Some(block_size) => operation(&mut AlignedReader::new(&mut *input, block_size)),
+            None => operation(input),
+        })
+    }
+
+    /// Internal method used to run an operation again if it fails with an error that may be
+    /// transient, up to the configured number of attempts
+    fn with_retries<T, O>(&self, mut operation: O) -> io::Result<T>
+    where
+        O: FnMut() -> io::Result<T>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation() {
+                Err(e) if attempt < self.retry_attempts_opt && is_retryable(&e) => {
+                    thread::sleep(self.retry_backoff_opt);
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        }
+    }
+}
+
+/// Internal function used to make sure that a payload location makes sense for the input file,
+/// for [`ConfReaderOptions::debug_assertions`]
+fn check_location<F>(magic_bytes: &[u8], input: &mut F, location: PayloadLocation) -> io::Result<()>
+where
+    F: Seek + ?Sized,
+{
+    let file_len = input.seek(SeekFrom::End(0))?;
+    let inconsistent = |message: String| Err(io::Error::new(io::ErrorKind::InvalidData, message));
+
+    if location.magic_offset + magic_bytes.len() as u64 > location.offset {
+        return inconsistent(format!(
+            "payload offset {} overlaps the magic bytes at offset {}",
+            location.offset, location.magic_offset
+        ));
+    }
+
+    if location.offset > file_len {
+        return inconsistent(format!(
+            "payload offset {} is past the end of the file ({file_len} bytes)",
+            location.offset
+        ));
+    }
+
+    match location.offset.checked_add(location.len) {
+        Some(end) if end <= file_len => Ok(()),
+        _ => inconsistent(format!(
+            "payload of {} bytes at offset {} extends past the end of the file ({file_len} bytes)",
+            location.len, location.offset
+        )),
+    }
+}
+
+/// Input file which can be both read and seeked, used to pass input files around without being
+/// generic over them, such as to the callback given to
+/// [`ConfReaderOptions::read_custom_framing`]. Implemented for everything that is [`Read`] and
+/// [`Seek`]
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// Internal function used to determine if an error is from the magic bytes not being found
+fn is_not_found(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<CatConfError>())
+        .is_some_and(|inner| matches!(inner, CatConfError::NotFound))
+}
+
+/// Internal function used to determine if an error is worth retrying the scan for
+fn is_retryable(error: &io::Error) -> bool {
+    let deadline_exceeded = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<CatConfError>())
+        .is_some_and(|inner| matches!(inner, CatConfError::DeadlineExceeded));
+
+    !deadline_exceeded
+        && matches!(
+            error.kind(),
+            io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        )
+}
+
+/// Useful if you just want to read from the current exe without bothering to use the builder
+///
+/// Not supported on WASI, see [`ConfReaderOptions::read_from_path`] instead
+///
+/// # Example
+///
+/// ```
+/// use catconf::read_from_exe;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let conf = read_from_exe(b"CATCONF", 4096)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_from_exe(magic_bytes: &[u8], window_size: i64) -> io::Result<Vec<u8>> {
+    let mut cur_exe = open_current_exe()?;
+    read_from_file(magic_bytes, window_size, &mut cur_exe)
+}
+
+/// Reads configuration appended to a shared library rather than the current executable, for
+/// platforms where the code using this crate is loaded as a library
+///
+/// On Android in particular, [`env::current_exe`] gives the app process (usually
+/// `app_process64`, started from the zygote) rather than the native library holding the
+/// application's code, so [`read_from_exe`] will never find the configuration. Instead, append
+/// the configuration to the `.so` and pass its path here, such as the path found by looking
+/// for the library in `/proc/self/maps`, or `nativeLibraryDir` from the app's
+/// `ApplicationInfo` joined with the library's file name
+///
+/// Android only extracts native libraries to `nativeLibraryDir` when the app is built with
+/// `extractNativeLibs="true"`; otherwise the library is loaded straight out of the APK and has
+/// no path of its own to read. Also note that appending to the library happens after it is
+/// built, so it has to be done before the APK is signed
+///
+/// # Example
+///
+/// ```no_run
+/// use catconf::read_from_shared_object;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let conf = read_from_shared_object(
+///     "/data/app/com.example.app/lib/arm64/libapp.so",
+///     b"CATCONF",
+///     4096,
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_from_shared_object<P: AsRef<Path>>(
+    path: P,
+    magic_bytes: &[u8],
+    window_size: i64,
+) -> io::Result<Vec<u8>> {
+    let mut library = OpenOptions::new().read(true).open(path)?;
+    read_from_file(magic_bytes, window_size, &mut library)
+}
+
+/// Allows for reading for configuration from the end of a file by looking for magic bytes
+///
+/// The configuration must not contain the magic bytes, or only the part of it after them may be
+/// returned; see [Magic bytes inside the configuration](crate#magic-bytes-inside-the-configuration)
+///
+/// # Example
+///
+/// ```no_run
+/// use catconf::read_from_file;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let buff = vec![0; 4096];
+/// # let mut input = std::io::Cursor::new(&buff);
+/// let conf = read_from_file(b"CATCONF", 2048, &mut input)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_from_file<F>(magic_bytes: &[u8], window_size: i64, input: &mut F) -> io::Result<Vec<u8>>
+where
+    F: Seek + Read,
+{
+    read_outermost_payload(&Scan::new(magic_bytes, window_size), input, |_| Ok(()))
+}
+
+/// Internal function used to read the payload once it has been found
+pub(crate) fn read_payload<F>(input: &mut F, location: PayloadLocation) -> io::Result<Vec<u8>>
+where
+    F: Seek + Read + ?Sized,
+{
+    input.seek(SeekFrom::Start(location.offset))?;
+    read_exact_vec(input, location.len)
+}
+
+/// Internal function used to read exactly `len` bytes into a new buffer. The buffer is only
+/// reserved rather than zeroed first, as it would be with `vec![0; len]`, since every byte is
+/// overwritten anyway. How much this saves depends on the allocator; the ignored
+/// `benchmarks_reserved_buffer` test compares the two
+pub(crate) fn read_exact_vec<F>(input: &mut F, len: u64) -> io::Result<Vec<u8>>
+where
+    F: Read + ?Sized,
+{
+    let mut buffer = Vec::with_capacity(len as usize);
+    input.take(len).read_to_end(&mut buffer)?;
+
+    if buffer.len() as u64 != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "failed to fill whole buffer",
+        ));
+    }
+
+    Ok(buffer)
+}
+
+/// Worst case IO needed to find configuration, as given by
+/// [`ConfReaderOptions::estimate_scan_cost`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanCostEstimate {
+    /// Number of windows read from the file
+    pub max_windows: u64,
+    /// Number of bytes read from the file
+    pub max_bytes_read: u64,
+}
+
+/// Where the configuration payload lives inside of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PayloadLocation {
+    /// Offset from the start of the file to the first of the magic bytes
+    pub(crate) magic_offset: u64,
+    /// Offset from the start of the file to the first byte after the magic bytes
+    pub(crate) offset: u64,
+    /// Amount of bytes from the offset until the end of the file
+    pub(crate) len: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::test_util::{CountingReader, OneByteReader};
+
+    use super::*;
+
+    /// Simplest use case
+    #[test]
+    fn pulls_basic_data() {
+        let input_data = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1,
+        ];
+        let header = [1, 2, 3, 4];
+        let data = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+
+        let mut buf = Cursor::new(&input_data);
+
+        assert_eq!(&read_from_file(&header, 16, &mut buf).unwrap(), &data);
+    }
+
+    /// Check to make sure reads occur when going across window boundaries. For instance, in
     /// this test the boundary will be split such that the first read reads "2,3,4,1,1,1...", missing
     /// the boundary
     #[test]
-    fn pulls_data_over_boundary() {
-        let input_data = [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-            1, 1, 1,
-        ];
-        let header = [1, 2, 3, 4];
-        let data = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+    fn pulls_data_over_boundary() {
+        let input_data = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1,
+        ];
+        let header = [1, 2, 3, 4];
+        let data = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+
+        let mut buf = Cursor::new(&input_data);
+
+        assert_eq!(&read_from_file(&header, 15, &mut buf).unwrap(), &data);
+    }
+
+    /// A payload inside the last window should be taken from the buffer the magic bytes were
+    /// found in without seeking back to it, both by read_from_file and by the default options,
+    /// and a longer one should still be read in full
+    #[test]
+    fn reads_payload_without_extra_seek() {
+        for payload_len in [20, 500] {
+            let mut input_data = vec![0; 1000];
+            input_data.extend([1, 2, 3, 4]);
+            input_data.extend((0..payload_len).map(|i| (i % 200) as u8 + 5));
+            let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+            options.window_size(32);
+
+            let mut options_buf = CountingReader::new(Cursor::new(&input_data));
+            let expected = options.read(&mut options_buf).unwrap();
+            let mut buf = CountingReader::new(Cursor::new(&input_data));
+
+            assert_eq!(
+                read_from_file(&[1, 2, 3, 4], 32, &mut buf).unwrap(),
+                expected
+            );
+            assert_eq!(expected, input_data[1004..]);
+
+            if payload_len == 20 {
+                assert_eq!((buf.seeks, buf.reads), (2, 1));
+                assert_eq!((options_buf.seeks, options_buf.reads), (2, 1));
+            }
+        }
+
+        // The checksum at the end of the window should be split off and still validated
+        let conf = [5; 20];
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(conf);
+        input_data.extend(checksum::crc32(&conf).to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(32).checksum(true);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), conf);
+        assert_eq!((buf.seeks, buf.reads), (2, 1));
+
+        let last = input_data.len() - 1;
+        input_data[last] ^= 0xff;
+        assert_eq!(
+            options
+                .read(&mut Cursor::new(&input_data))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    /// Reads returning a single byte at a time should still find the magic bytes in every
+    /// window and return the whole payload, in both scan directions
+    #[test]
+    fn handles_short_reads() {
+        let mut input_data = (0..1000).map(|i| (i % 200) as u8 + 5).collect::<Vec<u8>>();
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([6; 300]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..700).map(|i| (i % 50) as u8 + 10));
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(64);
+
+        let mut buf = OneByteReader(Cursor::new(&input_data));
+        assert_eq!(
+            read_from_file(&[1, 2, 3, 4], 64, &mut buf).unwrap(),
+            input_data[1308..]
+        );
+        assert_eq!(options.read(&mut buf).unwrap(), input_data[1308..]);
+        assert_eq!(
+            options.read_blocks(&mut buf).unwrap(),
+            [input_data[1308..].to_vec(), vec![6; 300]]
+        );
+
+        options.scan_direction(ScanDirection::Forward);
+        assert_eq!(options.read(&mut buf).unwrap(), [6; 300]);
+    }
+
+    /// Reading only part of the payload, both from the start and from the middle
+    #[test]
+    fn reads_payload_range() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(5..37);
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
 
         let mut buf = Cursor::new(&input_data);
 
-        assert_eq!(&read_from_file(&header, 15, &mut buf).unwrap(), &data);
+        assert_eq!(
+            options.read_payload_range(&mut buf, 0..16).unwrap(),
+            (5..21).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            options.read_payload_range(&mut buf, 10..20).unwrap(),
+            (15..25).collect::<Vec<u8>>()
+        );
+    }
+
+    /// A range that extends past the end of the payload should be rejected rather than
+    /// reading whatever is there
+    #[test]
+    fn rejects_out_of_bounds_range() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(0..32);
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        let mut buf = Cursor::new(&input_data);
+
+        let err = options.read_payload_range(&mut buf, 16..40).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    /// The distance from the end should be exactly the payload when nothing follows it, and
+    /// include any trailing bytes otherwise
+    #[test]
+    fn measures_payload_distance_from_end() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 24]);
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        let mut buf = Cursor::new(input_data.clone());
+        assert_eq!(options.payload_distance_from_end(&mut buf).unwrap(), 24);
+
+        input_data.extend([9; 8]);
+        let mut buf = Cursor::new(input_data);
+        assert_eq!(options.payload_distance_from_end(&mut buf).unwrap(), 32);
+    }
+
+    /// Reader which fails the first couple of reads, as a flaky network file might
+    struct FlakyReader<R> {
+        inner: R,
+        failures_left: usize,
+        kind: io::ErrorKind,
+    }
+
+    impl<R: Read> Read for FlakyReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(io::Error::new(self.kind, "flaky read"));
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for FlakyReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    /// A transient error should cause the scan to start over, while a fatal one should not
+    #[test]
+    fn retries_transient_errors() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 24]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16).retry_scan(3, Duration::ZERO);
+
+        let mut flaky = FlakyReader {
+            inner: Cursor::new(&input_data),
+            failures_left: 1,
+            kind: io::ErrorKind::TimedOut,
+        };
+        assert_eq!(options.read(&mut flaky).unwrap(), [1; 24]);
+
+        let mut fatal = FlakyReader {
+            inner: Cursor::new(&input_data),
+            failures_left: 1,
+            kind: io::ErrorKind::NotFound,
+        };
+        assert_eq!(
+            options.read(&mut fatal).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    /// Invalid UTF-8 should only be an error when it has been asked for
+    #[test]
+    fn requires_utf8_when_asked() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([0xff, 0xfe, 0x00]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(options.read(&mut buf).unwrap(), [0xff, 0xfe, 0x00]);
+
+        let err = options.require_utf8(true).read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(CatConfError::from(err), CatConfError::Utf8(_)));
+    }
+
+    /// With an offset pointer footer the payload can be anywhere, no matter the window size,
+    /// and is found with just two seeks
+    #[test]
+    fn locates_with_offset_pointer_footer() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 500]);
+        input_data.extend(1000u64.to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16).offset_pointer_footer(true);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.config_boundary(&mut buf).unwrap(), 1000);
+        assert_eq!(buf.seeks, 2);
+
+        assert_eq!(options.read(&mut buf).unwrap(), [1; 500]);
+    }
+
+    /// A pointer which doesn't lead to the magic bytes should be an error rather than
+    /// returning whatever happens to be there
+    #[test]
+    fn rejects_bad_offset_pointer() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 500]);
+        input_data.extend(900u64.to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.offset_pointer_footer(true);
+
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(
+            options.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    /// An offset pointer too large to add the magic bytes to should be rejected rather than
+    /// overflowing
+    #[test]
+    fn rejects_overflowing_offset_pointer() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([0xff; 8]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.offset_pointer_footer(true);
+
+        assert_eq!(
+            options
+                .read(&mut Cursor::new(&input_data))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    /// The trailing length should lead back to the magic bytes, and a length which doesn't
+    /// should be caught even though the magic bytes themselves are intact
+    #[test]
+    fn locates_with_magic_and_length() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 500]);
+        input_data.extend(500u64.to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.magic_and_length(true);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), [1; 500]);
+        assert_eq!(buf.seeks, 3);
+
+        for len in [499u64, 501, 10_000] {
+            let end = input_data.len() - 8;
+            input_data[end..].copy_from_slice(&len.to_le_bytes());
+
+            let mut buf = Cursor::new(&input_data);
+            assert_eq!(
+                options.read(&mut buf).unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+    }
+
+    /// The payload should be read from right after the stub, ignoring anything after it, and
+    /// a length running past the end of the file should be caught
+    #[test]
+    fn reads_length_prefixed_payload() {
+        let mut input_data = vec![0; 256];
+        input_data.extend(500u32.to_le_bytes());
+        input_data.extend((0..500).map(|i| (i % 200) as u8 + 5));
+        input_data.extend([9; 1000]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options
+            .prefix_length_framed(true)
+            .skip_prefix(256)
+            .debug_assertions(true);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), input_data[260..760]);
+        assert_eq!(buf.bytes_read, 504);
+
+        input_data.truncate(700);
+        assert_eq!(
+            options
+                .read(&mut Cursor::new(&input_data))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    /// Configuration containing the magic bytes, in several windows and right at its start and
+    /// end, should come back in full when framed with its length, while with plain suffix
+    /// framing only the part after the last copy comes back
+    #[test]
+    fn reads_config_containing_magic() {
+        let mut conf = vec![1, 2, 3, 4];
+        for i in 0..20 {
+            conf.extend((0..37).map(|j| (i * j % 200) as u8 + 5));
+            conf.extend([1, 2, 3, 4]);
+        }
+
+        for encoding in [LengthEncoding::U64, LengthEncoding::Varint] {
+            let mut file = Cursor::new(vec![0; 100]);
+            ConfWriterOptions::new(vec![1, 2, 3, 4])
+                .magic_and_length(true)
+                .length_encoding(encoding)
+                .write(&mut file, &conf)
+                .unwrap();
+
+            let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+                .window_size(16)
+                .magic_and_length(true)
+                .length_encoding(encoding)
+                .read(&mut file)
+                .unwrap();
+
+            assert_eq!(read, conf);
+        }
+
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(&conf);
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .window_size(16)
+            .read(&mut Cursor::new(&input_data))
+            .unwrap();
+
+        assert!(read.len() < conf.len());
+        assert!(conf.ends_with(&read));
+    }
+
+    /// Every block should be found, including ones which cross the boundary between windows
+    #[test]
+    fn reads_all_blocks() {
+        let mut input_data = vec![0; 40];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 27]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([6; 3]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([7; 10]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(8);
+
+        let mut buf = Cursor::new(&input_data);
+
+        assert_eq!(
+            options.read_blocks(&mut buf).unwrap(),
+            [vec![7; 10], vec![6; 3], vec![5; 27]]
+        );
+    }
+
+    /// A file full of the magic bytes should only give as many blocks as allowed, and say that
+    /// some were left out
+    #[test]
+    fn caps_matches() {
+        let mut input_data = vec![0; 40];
+        for i in 0..1000 {
+            input_data.extend([1, 2, 3, 4]);
+            input_data.push((i % 200) as u8 + 5);
+        }
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = Cursor::new(&input_data);
+        let (blocks, truncated) = options.read_blocks_capped(&mut buf).unwrap();
+        assert_eq!(blocks.len(), 1000);
+        assert!(!truncated);
+
+        options.max_matches(10);
+        let (blocks, truncated) = options.read_blocks_capped(&mut buf).unwrap();
+        assert_eq!(blocks.len(), 10);
+        assert_eq!(blocks[0], [(999 % 200) as u8 + 5]);
+        assert!(truncated);
+
+        options.max_matches(1000);
+        assert!(!options.read_blocks_capped(&mut buf).unwrap().1);
+    }
+
+    /// Block device which only allows reads of whole blocks
+    struct BlockDevice {
+        inner: Cursor<Vec<u8>>,
+        block_size: usize,
+    }
+
+    impl Read for BlockDevice {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !buf.len().is_multiple_of(self.block_size)
+                || !self.inner.position().is_multiple_of(self.block_size as u64)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "unaligned read from block device",
+                ));
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for BlockDevice {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    /// Reading from a block device should only work when reads are aligned
+    #[test]
+    fn reads_aligned_blocks() {
+        let mut input_data = vec![0; 3000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..4096 - 3004).map(|i| (i % 3) as u8 + 5));
+        let payload = input_data[3004..].to_vec();
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(100);
+
+        let mut device = BlockDevice {
+            inner: Cursor::new(input_data),
+            block_size: 512,
+        };
+        assert_eq!(
+            options.read(&mut device).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        options.align_to(512);
+        assert_eq!(options.read(&mut device).unwrap(), payload);
+        assert_eq!(
+            options.read_payload_range(&mut device, 10..20).unwrap(),
+            payload[10..20]
+        );
+    }
+
+    /// File which claims to be shorter than the data it actually returns
+    struct InconsistentFile {
+        inner: Cursor<Vec<u8>>,
+        claimed_len: u64,
+    }
+
+    impl Read for InconsistentFile {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for InconsistentFile {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            match pos {
+                SeekFrom::End(offset) => self.inner.seek(SeekFrom::Start(
+                    self.claimed_len.saturating_add_signed(offset),
+                )),
+                pos => self.inner.seek(pos),
+            }
+        }
+    }
+
+    /// An inconsistent file should be reported instead of reading a nonsensical payload
+    #[test]
+    fn debug_assertions_catch_inconsistent_seek() {
+        let mut input_data = vec![0; 20];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([1; 8]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16).debug_assertions(true);
+
+        let mut file = InconsistentFile {
+            inner: Cursor::new(input_data),
+            claimed_len: 10,
+        };
+        let err = options.config_boundary(&mut file).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("past the end of the file"));
+    }
+
+    /// Stand in for a binary packed with UPX, which is a small ELF stub followed by compressed
+    /// data and the UPX headers, with the configuration appended afterwards as overlay data
+    #[test]
+    fn reads_config_after_packed_binary() {
+        let mut packed = b"\x7fELF\x02\x01\x01\x00".to_vec();
+        packed.resize(0xe8, 0);
+        packed.extend(b"UPX!");
+        let mut state = 0x2545_f491u32;
+        packed.extend((0..8192).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        }));
+        packed.extend(b"UPX!\x0d\x16\x08\x07");
+
+        let mut input_data = packed.clone();
+        input_data.extend(b"CATCONF");
+        input_data.extend(b"packed conf");
+
+        let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+        let mut buf = Cursor::new(&input_data);
+
+        assert_eq!(options.read(&mut buf).unwrap(), b"packed conf");
+        assert_eq!(
+            options.config_boundary(&mut buf).unwrap(),
+            packed.len() as u64
+        );
+    }
+
+    /// Reading from a slice should give the same results as reading from a file, for each of
+    /// the framings and with validation
+    #[test]
+    fn reads_from_slice() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(b"suffix");
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        assert_eq!(options.read_slice(&input_data).unwrap(), b"suffix");
+
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(b"pointer");
+        input_data.extend(100u64.to_le_bytes());
+        options.offset_pointer_footer(true);
+
+        assert_eq!(options.read_slice(&input_data).unwrap(), b"pointer");
+
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([0xff, 0xfe]);
+        input_data.extend(100u64.to_le_bytes());
+        options.require_utf8(true);
+
+        assert!(matches!(
+            options.read_slice(&input_data),
+            Err(CatConfError::Utf8(_))
+        ));
+    }
+
+    /// A checksum should be left out of the payload whether or not it is validated, and a bad
+    /// checksum only caught when validating
+    #[test]
+    fn strips_checksum() {
+        let conf = b"checked conf";
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(conf);
+        input_data.extend((checksum::crc32(conf) ^ 1).to_le_bytes());
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = Cursor::new(&input_data);
+
+        options.checksum_present_skip_validation(true);
+        assert_eq!(options.read(&mut buf).unwrap(), conf);
+        assert_eq!(options.read_slice(&input_data).unwrap(), conf);
+
+        options.checksum(true);
+        let err = options.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            CatConfError::from(err),
+            CatConfError::ChecksumMismatch { .. }
+        ));
+    }
+
+    /// A window size of 0 should be raised to the length of the magic bytes, both for the
+    /// estimate and for the scan itself, instead of dividing by zero or never moving
+    #[test]
+    fn handles_zero_window_size() {
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 10]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(0);
+
+        let estimate = options.estimate_scan_cost(100);
+        assert_eq!(estimate, options.window_size(4).estimate_scan_cost(100));
+
+        options.window_size(0);
+        assert_eq!(
+            options.read(&mut Cursor::new(&input_data)).unwrap(),
+            [5; 10]
+        );
+    }
+
+    /// The estimate should match the reads actually done when the whole file has to be scanned
+    #[test]
+    fn estimates_scan_cost() {
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        for file_len in [0, 10, 32, 33, 100, 160, 1000] {
+            let mut buf = CountingReader::new(Cursor::new(vec![0; file_len]));
+            options.config_boundary(&mut buf).unwrap_err();
+
+            let estimate = options.estimate_scan_cost(file_len as u64);
+            // One seek to find the length of the file, then one to the start of each window;
+            // a window can take more than one read when it runs into the end of the file
+            assert_eq!(
+                estimate.max_windows,
+                buf.seeks as u64 - 1,
+                "file_len {file_len}"
+            );
+            assert_eq!(
+                estimate.max_bytes_read, buf.bytes_read,
+                "file_len {file_len}"
+            );
+        }
+    }
+
+    /// Only magic bytes on a block boundary should match, including when the boundary is near
+    /// the edge of a window
+    #[test]
+    fn matches_only_aligned_magic() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 8]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([6; 20]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(10);
+
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(options.read(&mut buf).unwrap(), [6; 20]);
+
+        options.aligned_magic(32);
+        assert_eq!(options.config_boundary(&mut buf).unwrap(), 64);
+        assert_eq!(options.read_blocks(&mut buf).unwrap().len(), 1);
+
+        options.aligned_magic(48);
+        assert_eq!(
+            options.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    /// Reading from a path should only need the standard file APIs that WASI provides
+    #[test]
+    fn read_from_path() {
+        let path = env::temp_dir().join(format!("catconf-read-from-path-{}", std::process::id()));
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 8]);
+        std::fs::write(&path, &input_data).unwrap();
+
+        let conf = ConfReaderOptions::new(vec![1, 2, 3, 4]).read_from_path(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(conf.unwrap(), [5; 8]);
+    }
+
+    /// Only the payloads should be compared, not the rest of the files, and the comparison
+    /// should work across chunk boundaries
+    #[test]
+    fn configs_equal() {
+        let conf = (0..20000).map(|i| (i % 3) as u8 + 5).collect::<Vec<_>>();
+        let mut a = vec![0; 64];
+        a.extend([1, 2, 3, 4]);
+        a.extend(&conf);
+        let mut b = vec![9; 100];
+        b.extend([1, 2, 3, 4]);
+        b.extend(&conf);
+        let mut changed = b.clone();
+        *changed.last_mut().unwrap() = 0;
+        let mut shorter = b.clone();
+        shorter.pop();
+
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        let mut a = Cursor::new(&a);
+
+        assert!(options.configs_equal(&mut a, &mut Cursor::new(&b)).unwrap());
+        assert!(!options
+            .configs_equal(&mut a, &mut Cursor::new(&changed))
+            .unwrap());
+        assert!(!options
+            .configs_equal(&mut a, &mut Cursor::new(&shorter))
+            .unwrap());
+    }
+
+    /// Payloads outside of the expected length range should be rejected
+    #[test]
+    fn expected_length_range() {
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.expected_length_range(4..8);
+        let read_len = |len: usize| {
+            let mut input_data = vec![0; 64];
+            input_data.extend([1, 2, 3, 4]);
+            input_data.extend(vec![5; len]);
+            options.read(&mut Cursor::new(&input_data))
+        };
+
+        for len in [4, 7] {
+            assert_eq!(read_len(len).unwrap(), vec![5; len]);
+        }
+
+        for len in [3, 8] {
+            assert!(matches!(
+                CatConfError::from(read_len(len).unwrap_err()),
+                CatConfError::LengthOutOfRange { len: found, range } if found == len as u64 && range == (4..8)
+            ));
+        }
+    }
+
+    /// The whole sidecar file should be the configuration, and options on the contents still
+    /// apply
+    #[test]
+    fn read_detached() {
+        let binary = env::temp_dir().join(format!("catconf-read-detached-{}", std::process::id()));
+        let mut sidecar = binary.clone().into_os_string();
+        sidecar.push(".catconf");
+        std::fs::write(&sidecar, [1, 2, 3, 4, 0xff]).unwrap();
+
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        let conf = options.read_detached(&binary);
+        let utf8_err = options.require_utf8(true).read_detached(&binary);
+        std::fs::remove_file(&sidecar).unwrap();
+
+        assert_eq!(conf.unwrap(), [1, 2, 3, 4, 0xff]);
+        assert!(matches!(
+            CatConfError::from(utf8_err.unwrap_err()),
+            CatConfError::Utf8(_)
+        ));
+        assert_eq!(
+            options.read_detached(&binary).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    /// A large minimum read size should need fewer reads to find the same configuration
+    #[test]
+    fn min_read_size() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 1000]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), [5; 1000]);
+        let small_reads = buf.reads;
+
+        options.min_read_size(512);
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), [5; 1000]);
+
+        assert!(buf.reads < small_reads);
+        assert_eq!(options.estimate_scan_cost(1068).max_windows, 4);
+    }
+
+    /// Scanning forward should find the innermost block without reading through the large
+    /// blocks after it, and the match index should pick blocks in either direction
+    #[test]
+    fn scans_forward() {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 10]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([6; 5000]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([7; 5000]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options
+            .window_size(16)
+            .scan_direction(ScanDirection::Forward);
+
+        let mut buf = CountingReader::new(Cursor::new(&input_data));
+        assert_eq!(options.read(&mut buf).unwrap(), [5; 10]);
+        assert!(buf.bytes_read < 256);
+
+        options.match_index(2);
+        assert_eq!(options.read(&mut buf).unwrap(), [7; 5000]);
+
+        options.scan_direction(ScanDirection::Backward);
+        assert_eq!(options.read(&mut buf).unwrap(), [5; 10]);
+        options.match_index(1);
+        assert_eq!(options.read(&mut buf).unwrap(), [6; 5000]);
+
+        options.match_index(3);
+        assert_eq!(
+            options.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        options.scan_direction(ScanDirection::Forward);
+        assert_eq!(
+            options.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    /// With two blocks in the same window, scanning backward should give the outermost block
+    /// alone, the same as the first of the blocks read, and the match index should count from
+    /// it
+    #[test]
+    fn scans_backward_within_one_window() {
+        let mut input_data = vec![0; 4096];
+        input_data.extend(b"CATCONFbase");
+        input_data.extend(b"CATCONFoverride");
+        let mut options = ConfReaderOptions::new(b"CATCONF".to_vec());
+
+        let read = options.read(&mut Cursor::new(&input_data)).unwrap();
+        assert_eq!(read, b"override");
+        assert_eq!(
+            options.read_blocks(&mut Cursor::new(&input_data)).unwrap()[0],
+            read
+        );
+
+        options.match_index(1);
+        assert_eq!(
+            options.read(&mut Cursor::new(&input_data)).unwrap(),
+            b"base"
+        );
+    }
+
+    /// Payloads larger than a single read should be read in full, and a payload cut short
+    /// should still be an error
+    #[test]
+    fn reads_large_payload() {
+        let len = 100_000;
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..len).map(|i| (i % 200) as u8 + 5));
+        let mut location = PayloadLocation {
+            magic_offset: 64,
+            offset: 68,
+            len: len as u64,
+        };
+
+        let conf = read_payload(&mut Cursor::new(&input_data), location).unwrap();
+        assert!(conf == input_data[68..]);
+
+        location.len += 1;
+        assert_eq!(
+            read_payload(&mut Cursor::new(&input_data), location)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    /// Compares reading a large payload into a reserved buffer against reading it into a
+    /// zeroed one, as the old reader did. Only run on request, as timings aren't meaningful in
+    /// a debug build or next to other tests: `cargo test --release -- --ignored
+    /// benchmarks_reserved_buffer --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmarks_reserved_buffer() {
+        let len = 64 * 1024 * 1024;
+        let input_data = vec![5; len];
+        let rounds = 10;
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let conf = read_exact_vec(&mut Cursor::new(&input_data), len as u64).unwrap();
+            std::hint::black_box(conf);
+        }
+        let reserved = start.elapsed() / rounds;
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let mut conf = vec![0; len];
+            Cursor::new(&input_data).read_exact(&mut conf).unwrap();
+            std::hint::black_box(conf);
+        }
+        let zeroed = start.elapsed() / rounds;
+
+        eprintln!("{len} bytes: reserved buffer {reserved:?}, zeroed buffer {zeroed:?}");
+    }
+
+    /// A custom footer parsed by the callback should give the payload, and a callback giving
+    /// a payload past the end of the file should be caught before anything is read
+    #[test]
+    fn reads_custom_framing() {
+        // binary || payload || u32 payload length || "CF"
+        let mut input_data = vec![0; 1000];
+        input_data.extend([5; 300]);
+        input_data.extend(300u32.to_le_bytes());
+        input_data.extend(b"CF");
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        let parse_footer = |input: &mut dyn ReadSeek| {
+            let footer_start = input.seek(SeekFrom::End(-6))?;
+            let len = u64::from(framing::read_u32(input)?);
+            framing::verify_magic(b"CF", input, "custom footer not found")?;
+
+            Ok((footer_start - len, len))
+        };
+
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(
+            options.read_custom_framing(&mut buf, parse_footer).unwrap(),
+            [5; 300]
+        );
+
+        let err = options
+            .read_custom_framing(&mut buf, |_| Ok((1200, 200)))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// The file length returned should be the length of the whole file, not just the payload
+    #[test]
+    fn read_with_file_len() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 20]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let (conf, file_len) = options
+            .read_with_file_len(&mut Cursor::new(&input_data))
+            .unwrap();
+
+        assert_eq!(conf, [5; 20]);
+        assert_eq!(file_len, input_data.len() as u64);
+    }
+
+    /// A file without the magic bytes should give `None` in either scan direction, while the
+    /// payload and real IO errors should still come back
+    #[test]
+    fn reads_optional() {
+        let mut input_data = vec![0; 1000];
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(options.read_optional(&mut buf).unwrap(), None);
+        let err = options.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(CatConfError::from(err), CatConfError::NotFound));
+
+        options.scan_direction(ScanDirection::Forward);
+        assert_eq!(options.read_optional(&mut buf).unwrap(), None);
+
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 20]);
+        let mut buf = Cursor::new(&input_data);
+        assert_eq!(options.read_optional(&mut buf).unwrap(), Some(vec![5; 20]));
+
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::PermissionDenied.into())
+            }
+        }
+
+        impl Seek for Broken {
+            fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+                Ok(1000)
+            }
+        }
+
+        assert_eq!(
+            options.read_optional(&mut Broken).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+
+    /// A deadline that has already passed should stop the scan before it reads anything,
+    /// without being retried
+    #[test]
+    fn read_with_deadline() {
+        let input_data = vec![0; 16 * 1024 * 1024];
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.retry_scan(3, Duration::from_secs(10));
+
+        let mut input = CountingReader::new(Cursor::new(&input_data));
+        let err = options
+            .read_with_deadline(&mut input, Instant::now())
+            .unwrap_err();
+
+        assert!(matches!(err, CatConfError::DeadlineExceeded));
+        assert_eq!(input.reads, 0);
+
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 20]);
+        let deadline = Instant::now() + Duration::from_secs(60);
+
+        assert_eq!(
+            options
+                .read_with_deadline(&mut Cursor::new(&input_data), deadline)
+                .unwrap(),
+            [5; 20]
+        );
+    }
+
+    /// Configuration appended to a shared library should be read from its path
+    #[test]
+    fn read_from_shared_object() {
+        let path = env::temp_dir().join(format!("libcatconf-{}.so", std::process::id()));
+        let mut input_data = b"\x7fELF".to_vec();
+        input_data.extend([0; 60]);
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 8]);
+        std::fs::write(&path, &input_data).unwrap();
+
+        let conf = super::read_from_shared_object(&path, &[1, 2, 3, 4], 16);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(conf.unwrap(), [5; 8]);
     }
 }
@@ -0,0 +1,95 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{CatConfError, ConfReaderOptions};
+
+/// The magic bytes used by convention, such as in the examples throughout this crate. Using
+/// this means the configuration can be appended with just:
+///
+/// `cat binary <(echo -n "CATCONF") conf > confedbinary`
+pub const DEFAULT_MAGIC: &[u8] = b"CATCONF";
+
+/// Shortest magic bytes accepted by [`validate_magic`]
+const MIN_MAGIC_LEN: usize = 4;
+
+/// Checks that the magic bytes given are unlikely to show up by accident, either in the
+/// original binary or in the configuration. The magic bytes are rejected if they are:
+///
+/// 1. Empty, which would match anywhere
+/// 2. Shorter than 4 bytes, which would likely match somewhere in a large binary
+/// 3. The same byte repeated, such as padding or a run of spaces, which binaries and text
+///    are full of
+///
+/// # Example
+///
+/// ```
+/// use catconf::{validate_magic, DEFAULT_MAGIC};
+///
+/// assert!(validate_magic(DEFAULT_MAGIC).is_ok());
+/// assert!(validate_magic(b"").is_err());
+/// assert!(validate_magic(b"\0\0\0\0\0\0").is_err());
+/// ```
+pub fn validate_magic(magic_bytes: &[u8]) -> Result<(), CatConfError> {
+    let reason = if magic_bytes.is_empty() {
+        "magic bytes are empty"
+    } else if magic_bytes.len() < MIN_MAGIC_LEN {
+        "magic bytes are shorter than 4 bytes"
+    } else if magic_bytes.iter().all(|byte| *byte == magic_bytes[0]) {
+        "magic bytes are a single repeated byte"
+    } else {
+        return Ok(());
+    };
+
+    Err(CatConfError::InvalidMagic(reason))
+}
+
+impl ConfReaderOptions {
+    /// Create a new ConfReaderOptions builder using [`DEFAULT_MAGIC`] for the magic bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::with_default_magic().read_from_exe()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_default_magic() -> Self {
+        ConfReaderOptions::new(DEFAULT_MAGIC.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default magic bytes should pass validation, and ones likely to match by accident
+    /// shouldn't
+    #[test]
+    fn validates_magic() {
+        assert!(validate_magic(DEFAULT_MAGIC).is_ok());
+        assert!(validate_magic(&[1, 2, 3, 4]).is_ok());
+
+        for magic in [&b""[..], b"CAT", b"    "] {
+            assert!(matches!(
+                validate_magic(magic),
+                Err(CatConfError::InvalidMagic(_))
+            ));
+        }
+    }
+}
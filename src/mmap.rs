@@ -0,0 +1,118 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fmt, fs::OpenOptions, io, ops::Deref, path::Path};
+
+use memmap2::Mmap;
+
+use crate::ConfReaderOptions;
+
+/// Configuration found in a memory mapped file by [`ConfReaderOptions::read_mmap_slice`]
+///
+/// This owns the mapping of the whole file, but dereferences to just the configuration, so it
+/// can be kept around for the life of the program and used as a `&[u8]` without copying the
+/// configuration or keeping the file open
+pub struct MmapSlice {
+    mmap: Mmap,
+    offset: usize,
+    len: usize,
+}
+
+impl Deref for MmapSlice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap[self.offset..self.offset + self.len]
+    }
+}
+
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for MmapSlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmapSlice")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl ConfReaderOptions {
+    /// Memory maps the file at the path given and finds the configuration in it, honoring the
+    /// same options as [`ConfReaderOptions::read_slice`]. Only the pages of the file which are
+    /// actually touched are read, rather than copying the configuration out up front
+    ///
+    /// Like any memory mapped file, the contents can change out from under the mapping if the
+    /// file is modified while the [`MmapSlice`] is alive; it is meant for files which are not
+    /// written to once deployed, such as the current executable
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_mmap_slice(std::env::current_exe()?)?;
+    ///
+    /// println!("{} bytes of configuration", conf.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_mmap_slice<P: AsRef<Path>>(&self, path: P) -> io::Result<MmapSlice> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        // SAFETY: the mapping is only ever read from, and the documentation above warns that
+        // the file must not be modified while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let conf = self.read_slice(&mmap)?;
+        let offset = conf.as_ptr() as usize - mmap.as_ptr() as usize;
+        let len = conf.len();
+
+        Ok(MmapSlice { mmap, offset, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// The slice should only be the payload, and still be readable after the file used to
+    /// map it is closed
+    #[test]
+    fn reads_mmap_slice() {
+        let path = env::temp_dir().join(format!("catconf-read-mmap-slice-{}", std::process::id()));
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 8]);
+        std::fs::write(&path, &input_data).unwrap();
+
+        // The file is opened and closed again within read_mmap_slice
+        let slice = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .read_mmap_slice(&path)
+            .unwrap();
+        assert_eq!(&*slice, [5; 8]);
+
+        drop(slice);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
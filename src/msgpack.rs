@@ -0,0 +1,92 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::prelude::*;
+
+use serde::de::DeserializeOwned;
+
+use crate::{CatConfError, ConfReaderOptions};
+
+impl ConfReaderOptions {
+    /// Reads the configuration and parses it as MessagePack, for when a compact binary
+    /// configuration is preferred over JSON
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> Result<(), catconf::CatConfError> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend([0x92, 0xa4, b'h', b'o', b's', b't', 0xcd, 0x1f, 0x90]);
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let (host, port): (String, u16) = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .read_msgpack(&mut input)?;
+    /// # assert_eq!((host.as_str(), port), ("host", 8080));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_msgpack<T, F>(&self, input: &mut F) -> Result<T, CatConfError>
+    where
+        T: DeserializeOwned,
+        F: Seek + Read,
+    {
+        let raw = self.read(input)?;
+
+        Ok(rmp_serde::from_slice(&raw)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Conf {
+        name: String,
+        port: u16,
+        peers: Vec<String>,
+    }
+
+    /// A struct serialized as MessagePack should come back out the same, and anything else
+    /// should be reported as a MessagePack error
+    #[test]
+    fn round_trips_msgpack() {
+        let conf = Conf {
+            name: "app".into(),
+            port: 8080,
+            peers: vec!["a".into(), "b".into()],
+        };
+        let mut input_data = vec![0; 64];
+        input_data.extend(b"CATCONF");
+        input_data.extend(rmp_serde::to_vec(&conf).unwrap());
+
+        let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+        let read: Conf = options.read_msgpack(&mut Cursor::new(&input_data)).unwrap();
+        assert_eq!(read, conf);
+
+        input_data.truncate(64 + 7 + 1);
+        assert!(matches!(
+            options.read_msgpack::<Conf, _>(&mut Cursor::new(&input_data)),
+            Err(CatConfError::MessagePack(_))
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*};
+
+/// Reader which provides only the bytes of the original binary, stopping at the magic bytes
+/// that delimit the configuration. Useful for hashing or otherwise processing the binary as if
+/// the configuration had never been appended
+///
+/// Created with [`ConfReaderOptions::original_binary_reader`](crate::ConfReaderOptions::original_binary_reader)
+pub struct OriginalBinaryReader<F> {
+    input: io::Take<F>,
+}
+
+impl<F> OriginalBinaryReader<F> {
+    /// Internal constructor used once the input is already seeked to the start of the file
+    pub(crate) fn new(input: F, boundary: u64) -> Self
+    where
+        F: Read,
+    {
+        OriginalBinaryReader {
+            input: input.take(boundary),
+        }
+    }
+
+    /// Gives back the wrapped input file
+    pub fn into_inner(self) -> F {
+        self.input.into_inner()
+    }
+}
+
+impl<F: Read> Read for OriginalBinaryReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ConfReaderOptions;
+
+    use super::*;
+
+    /// The original binary should come back exactly, without the magic bytes or the
+    /// configuration
+    #[test]
+    fn reads_only_original_binary() {
+        let binary = (0..100).collect::<Vec<u8>>();
+        let mut input_data = binary.clone();
+        input_data.extend([200, 201, 202, 203]);
+        input_data.extend([1; 24]);
+
+        let mut options = ConfReaderOptions::new(vec![200, 201, 202, 203]);
+        options.window_size(16);
+
+        let mut reader = options
+            .original_binary_reader(Cursor::new(&input_data))
+            .unwrap();
+        let mut output = vec![];
+        reader.read_to_end(&mut output).unwrap();
+
+        assert_eq!(output, binary);
+    }
+}
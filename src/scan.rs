@@ -0,0 +1,377 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    io::{self, prelude::*, SeekFrom},
+    time::Instant,
+};
+
+use crate::{read_payload, CatConfError, PayloadLocation};
+
+/// Which end of the file the scan for the magic bytes starts from
+///
+/// When configuration has been appended more than once, scanning backward finds the outermost
+/// (last appended) block first, and scanning forward finds the innermost (first appended)
+/// block first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanDirection {
+    /// Scan from the end of the file towards the start
+    #[default]
+    Backward,
+    /// Scan from the start of the file towards the end
+    Forward,
+}
+
+/// Internal description of how to scan a file for the magic bytes
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Scan<'a> {
+    /// The magic bytes to look for
+    pub(crate) magic_bytes: &'a [u8],
+    /// Size of each step taken through the file; twice this is read at a time
+    pub(crate) window_size: i64,
+    /// Only offsets which are a multiple of this are checked for the magic bytes
+    pub(crate) magic_alignment: u64,
+    /// Which end of the file to start scanning from
+    pub(crate) direction: ScanDirection,
+    /// How many matches of the magic bytes to skip over, in the direction of the scan
+    pub(crate) match_index: usize,
+    /// Time by which the scan has to finish, checked before each window is read
+    pub(crate) deadline: Option<Instant>,
+}
+
+impl<'a> Scan<'a> {
+    pub(crate) fn new(magic_bytes: &'a [u8], window_size: i64) -> Self {
+        Scan {
+            magic_bytes,
+            window_size,
+            magic_alignment: 1,
+            direction: ScanDirection::Backward,
+            match_index: 0,
+            deadline: None,
+        }
+    }
+
+    /// Fails with [`CatConfError::DeadlineExceeded`] once the deadline has passed
+    fn check_deadline(&self) -> io::Result<()> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                Err(CatConfError::DeadlineExceeded.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Where the buffer for the window at `window_index`, counting from 1 at the end of a file
+    /// `file_len` bytes long, starts when scanning for the outermost payload. Each buffer
+    /// holds two windows, so that magic bytes across the boundary are still found
+    pub(crate) fn outermost_window_start(&self, file_len: i64, window_index: i64) -> i64 {
+        (file_len - (window_index + 1) * self.window_size).max(0)
+    }
+
+    /// Positions within a buffer read from the file at `buffer_start` where the magic bytes
+    /// are, from the start of the buffer to the end
+    pub(crate) fn matches<'b>(
+        &'b self,
+        buffer: &'b [u8],
+        buffer_start: u64,
+    ) -> impl Iterator<Item = usize> + 'b {
+        let first = ((self.magic_alignment - buffer_start % self.magic_alignment)
+            % self.magic_alignment) as usize;
+        let last = buffer.len().checked_sub(self.magic_bytes.len());
+
+        last.into_iter()
+            .flat_map(move |last| (first..=last).step_by(self.magic_alignment as usize))
+            .filter(move |pos| &buffer[*pos..*pos + self.magic_bytes.len()] == self.magic_bytes)
+    }
+}
+
+/// Internal function used to fill as much of the buffer as the input has left, as a single
+/// read is allowed to return fewer bytes than asked for even before the end of the input.
+/// Returns how many bytes were read, which is only less than the length of the buffer at the
+/// end of the input
+pub(crate) fn read_full<F>(input: &mut F, buffer: &mut [u8]) -> io::Result<usize>
+where
+    F: Read + ?Sized,
+{
+    let mut filled = 0;
+
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(bytes_read) => filled += bytes_read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Internal function used to scan the file for the magic bytes, without reading the payload
+pub(crate) fn locate_payload<F>(scan: &Scan, input: &mut F) -> io::Result<PayloadLocation>
+where
+    F: Seek + Read + ?Sized,
+{
+    let mut locations = match (scan.direction, scan.match_index) {
+        (ScanDirection::Backward, 0) => return locate_outermost_payload(scan, input),
+        (ScanDirection::Backward, index) => locate_payloads_backward(scan, input, index + 1)?,
+        (ScanDirection::Forward, index) => locate_payloads_forward(scan, input, index + 1)?,
+    };
+
+    if locations.is_empty() {
+        return Err(CatConfError::NotFound.into());
+    }
+
+    if locations.len() <= scan.match_index {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "found fewer copies of the magic bytes than the match index",
+        ));
+    }
+
+    Ok(locations.swap_remove(scan.match_index))
+}
+
+/// Internal function used to find the last copy of the magic bytes in the file, where the
+/// payload continues to the end of the file
+fn locate_outermost_payload<F>(scan: &Scan, input: &mut F) -> io::Result<PayloadLocation>
+where
+    F: Seek + Read + ?Sized,
+{
+    Ok(scan_outermost_payload(scan, input)?.0)
+}
+
+/// Internal function used to find and read the last payload in the file in one go. The length
+/// of the file is only found once at the start of the scan, and when the window the magic
+/// bytes were found in reaches the end of the file, the payload is taken from it instead of
+/// seeking back and reading it again. The location found is passed to `check` before the
+/// payload is read, so that it can be rejected without reading it
+pub(crate) fn read_outermost_payload<F, C>(
+    scan: &Scan,
+    input: &mut F,
+    check: C,
+) -> io::Result<Vec<u8>>
+where
+    F: Seek + Read + ?Sized,
+    C: FnOnce(PayloadLocation) -> io::Result<()>,
+{
+    let (location, window_start, mut window) = scan_outermost_payload(scan, input)?;
+    check(location)?;
+
+    if window_start + window.len() as u64 == location.offset + location.len {
+        window.drain(..(location.offset - window_start) as usize);
+        return Ok(window);
+    }
+
+    read_payload(input, location)
+}
+
+/// Internal function used to scan for the last copy of the magic bytes, also returning where
+/// the window they were found in starts and the bytes read for it
+fn scan_outermost_payload<F>(
+    scan: &Scan,
+    input: &mut F,
+) -> io::Result<(PayloadLocation, u64, Vec<u8>)>
+where
+    F: Seek + Read + ?Sized,
+{
+    let window_size = scan.window_size;
+    let buffer_size = window_size * 2;
+    let mut current_window_index: i64 = 1;
+    let mut current_read_buffer = vec![0u8; buffer_size as usize];
+    let file_len = input.seek(SeekFrom::End(0))? as i64;
+
+    loop {
+        scan.check_deadline()?;
+
+        let window_start = scan.outermost_window_start(file_len, current_window_index);
+        input.seek(SeekFrom::Start(window_start as u64))?;
+        let bytes_read = read_full(input, &mut current_read_buffer)?;
+
+        let found = outermost_location(
+            scan,
+            &current_read_buffer[..bytes_read],
+            window_start as u64,
+            file_len as u64,
+        );
+
+        if let Some(location) = found {
+            current_read_buffer.truncate(bytes_read);
+
+            break Ok((location, window_start as u64, current_read_buffer));
+        }
+
+        if window_start == 0 {
+            break Err(CatConfError::NotFound.into());
+        }
+
+        current_window_index += 1;
+    }
+}
+
+/// Internal function used to pick the copy of the magic bytes the outermost payload starts at
+/// out of a buffer read from `buffer_start` in a file `file_len` bytes long. This is the last
+/// copy in the buffer, the same as the first one found by [`locate_payloads_backward`], as any
+/// earlier copy is either part of the payload after it or overlapped by it
+pub(crate) fn outermost_location(
+    scan: &Scan,
+    buffer: &[u8],
+    buffer_start: u64,
+    file_len: u64,
+) -> Option<PayloadLocation> {
+    let pos = scan.matches(buffer, buffer_start).last()? as u64;
+    let offset = buffer_start + pos + scan.magic_bytes.len() as u64;
+
+    // An input which reads more than the length it claims is caught by the debug assertions
+    Some(PayloadLocation {
+        magic_offset: buffer_start + pos,
+        offset,
+        len: file_len.saturating_sub(offset),
+    })
+}
+
+/// Internal function used to scan the whole file for every copy of the magic bytes, returning
+/// the payloads in between them from the end of the file to the start
+pub(crate) fn locate_all_payloads<F>(scan: &Scan, input: &mut F) -> io::Result<Vec<PayloadLocation>>
+where
+    F: Seek + Read + ?Sized,
+{
+    locate_payloads_backward(scan, input, usize::MAX)
+}
+
+/// Internal function used to scan the file from the end for up to `limit` payloads, returned
+/// from the end of the file to the start
+pub(crate) fn locate_payloads_backward<F>(
+    scan: &Scan,
+    input: &mut F,
+    limit: usize,
+) -> io::Result<Vec<PayloadLocation>>
+where
+    F: Seek + Read + ?Sized,
+{
+    let window_size = scan.window_size;
+    let magic_len = scan.magic_bytes.len() as i64;
+    let buffer_size = window_size * 2;
+    let mut current_read_buffer = vec![0u8; buffer_size as usize];
+    let file_len = input.seek(SeekFrom::End(0))? as i64;
+    let mut window_end = file_len;
+    let mut payload_end = file_len;
+    let mut locations = vec![];
+
+    while window_end > 0 && locations.len() < limit {
+        scan.check_deadline()?;
+
+        let window_start = (window_end - window_size).max(0);
+        input.seek(SeekFrom::Start(window_start as u64))?;
+        let bytes_read = read_full(input, &mut current_read_buffer)?;
+
+        // Only matches starting before the previous window are new, the rest of the buffer is
+        // there so that magic bytes across the boundary are still found
+        let starts = (window_end - window_start) as usize;
+        let matches = scan
+            .matches(&current_read_buffer[..bytes_read], window_start as u64)
+            .take_while(|pos| *pos < starts)
+            .map(|pos| window_start + pos as i64)
+            .collect::<Vec<_>>();
+
+        for magic_offset in matches.into_iter().rev() {
+            let offset = magic_offset + magic_len;
+
+            if offset > payload_end {
+                continue;
+            }
+
+            locations.push(PayloadLocation {
+                magic_offset: magic_offset as u64,
+                offset: offset as u64,
+                len: (payload_end - offset) as u64,
+            });
+            payload_end = magic_offset;
+
+            if locations.len() == limit {
+                break;
+            }
+        }
+
+        window_end = window_start;
+    }
+
+    Ok(locations)
+}
+
+/// Internal function used to scan the file from the start for up to `limit` payloads, returned
+/// from the start of the file to the end. Each payload stops at the magic bytes after it, so
+/// the scan continues until one more copy of the magic bytes is found or the end of the file
+fn locate_payloads_forward<F>(
+    scan: &Scan,
+    input: &mut F,
+    limit: usize,
+) -> io::Result<Vec<PayloadLocation>>
+where
+    F: Seek + Read + ?Sized,
+{
+    let window_size = scan.window_size;
+    let magic_len = scan.magic_bytes.len() as i64;
+    let buffer_size = window_size * 2;
+    let mut current_read_buffer = vec![0u8; buffer_size as usize];
+    let file_len = input.seek(SeekFrom::End(0))? as i64;
+    let mut window_start = 0;
+    let mut magic_offsets = vec![];
+    // Copies of the magic bytes overlapping the previous ones are part of its payload
+    let mut payload_start = 0;
+
+    while window_start < file_len && magic_offsets.len() <= limit {
+        scan.check_deadline()?;
+
+        input.seek(SeekFrom::Start(window_start as u64))?;
+        let bytes_read = read_full(input, &mut current_read_buffer)?;
+
+        // Only matches starting within this window are new, the rest of the buffer is there
+        // so that magic bytes across the boundary are still found
+        let matches = scan
+            .matches(&current_read_buffer[..bytes_read], window_start as u64)
+            .take_while(|pos| (*pos as i64) < window_size)
+            .map(|pos| window_start + pos as i64);
+
+        for magic_offset in matches {
+            if magic_offset < payload_start {
+                continue;
+            }
+
+            magic_offsets.push(magic_offset);
+            payload_start = magic_offset + magic_len;
+
+            if magic_offsets.len() > limit {
+                break;
+            }
+        }
+
+        window_start += window_size;
+    }
+
+    let ends = magic_offsets.iter().skip(1).copied().chain([file_len]);
+
+    Ok(magic_offsets
+        .iter()
+        .zip(ends)
+        .take(limit)
+        .map(|(&magic_offset, end)| PayloadLocation {
+            magic_offset: magic_offset as u64,
+            offset: (magic_offset + magic_len) as u64,
+            len: (end - magic_offset - magic_len) as u64,
+        })
+        .collect())
+}
@@ -0,0 +1,343 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Indexed configuration, where several named sections are appended together along with an
+//! index of them:
+//!
+//! `binary || magic bytes || section data || index || u64 length of index`
+//!
+//! The index is a little endian `u32` count of sections, followed by each section in the
+//! order their data appears as a little endian `u16` name length, the UTF-8 name, and a little
+//! endian `u64` data length. The data of the sections directly follows the magic bytes
+//! without any padding, so the offset of each section is found by adding up the lengths of
+//! the sections before it
+
+use std::io::{self, prelude::*, SeekFrom};
+
+use crate::{
+    framing::{read_u64, verify_magic, FOOTER_LEN},
+    read_exact_vec, ConfReaderOptions, ReadSeek,
+};
+
+/// A single section listed in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Section {
+    name: String,
+    offset: u64,
+    len: u64,
+}
+
+/// Internal function used to read the index at the end of the file, checking that the magic
+/// bytes are in front of the section data it describes
+pub(crate) fn read_index(magic_bytes: &[u8], input: &mut dyn ReadSeek) -> io::Result<Vec<Section>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+
+    let footer_start = input.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    let index_len = read_u64(input)?;
+    let index_start = footer_start
+        .checked_sub(index_len)
+        .ok_or_else(|| invalid("index is longer than the file"))?;
+
+    input.seek(SeekFrom::Start(index_start))?;
+    let index = read_exact_vec(input, index_len)?;
+    let mut index = &index[..];
+
+    let mut count = [0; 4];
+    index.read_exact(&mut count)?;
+
+    let mut entries = vec![];
+    for _ in 0..u32::from_le_bytes(count) {
+        let mut name_len = [0; 2];
+        index.read_exact(&mut name_len)?;
+        let name = read_exact_vec(&mut index, u16::from_le_bytes(name_len).into())?;
+        let name = String::from_utf8(name).map_err(|_| invalid("section name is not UTF-8"))?;
+
+        entries.push((name, read_u64(&mut index)?));
+    }
+
+    if !index.is_empty() {
+        return Err(invalid("index is longer than the sections it lists"));
+    }
+
+    let data_len = entries
+        .iter()
+        .try_fold(0u64, |total, (_, len)| total.checked_add(*len))
+        .ok_or_else(|| invalid("sections are longer than the file"))?;
+    let data_start = index_start
+        .checked_sub(data_len)
+        .ok_or_else(|| invalid("sections are longer than the file"))?;
+    let magic_offset = data_start
+        .checked_sub(magic_bytes.len() as u64)
+        .ok_or_else(|| invalid("sections are longer than the file"))?;
+
+    input.seek(SeekFrom::Start(magic_offset))?;
+    verify_magic(
+        magic_bytes,
+        input,
+        "magic bytes not found before the sections",
+    )?;
+
+    let mut offset = data_start;
+    Ok(entries
+        .into_iter()
+        .map(|(name, len)| {
+            let section = Section { name, offset, len };
+            offset += len;
+            section
+        })
+        .collect())
+}
+
+impl ConfReaderOptions {
+    /// Lists the names of the sections in a file written with
+    /// [`ConfWriterOptions::write_sections`](crate::ConfWriterOptions::write_sections), in the order they were written. Only the index
+    /// at the end of the file is read, not any of the section data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .write_sections(&mut file, &[("app", b"port=8080"), ("log", b"level=info")])?;
+    ///
+    /// let names = ConfReaderOptions::new(b"CATCONF".to_vec()).list_sections(&mut file)?;
+    /// # assert_eq!(names, ["app", "log"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn list_sections<F>(&self, input: &mut F) -> io::Result<Vec<String>>
+    where
+        F: Seek + Read,
+    {
+        let sections = self.with_input(input, |input| read_index(&self.magic_bytes_opt, input))?;
+
+        Ok(sections.into_iter().map(|section| section.name).collect())
+    }
+
+    /// Reads a single section by name from a file written with
+    /// [`ConfWriterOptions::write_sections`](crate::ConfWriterOptions::write_sections)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .write_sections(&mut file, &[("app", b"port=8080"), ("log", b"level=info")])?;
+    ///
+    /// let log = ConfReaderOptions::new(b"CATCONF".to_vec()).read_section(&mut file, "log")?;
+    /// # assert_eq!(log, b"level=info");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_section<F>(&self, input: &mut F, name: &str) -> io::Result<Vec<u8>>
+    where
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            let section = read_index(&self.magic_bytes_opt, input)?
+                .into_iter()
+                .find(|section| section.name == name)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no section named {name:?}"),
+                    )
+                })?;
+
+            input.seek(SeekFrom::Start(section.offset))?;
+            read_exact_vec(input, section.len)
+        })
+    }
+
+    /// Reads the index of a file written with
+    /// [`ConfWriterOptions::write_sections`](crate::ConfWriterOptions::write_sections) once,
+    /// and returns an iterator which reads each section's name and data only when it is
+    /// reached, so that only one section is held in memory at a time and stopping early skips
+    /// reading the rest
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, ConfWriterOptions};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .write_sections(&mut file, &[("app", b"port=8080"), ("log", b"level=info")])?;
+    ///
+    /// let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+    /// for section in options.sections_iter(&mut file)? {
+    ///     let (name, data) = section?;
+    ///     println!("{name}: {} bytes", data.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sections_iter<'a, F>(
+        &'a self,
+        mut input: F,
+    ) -> io::Result<impl Iterator<Item = io::Result<(String, Vec<u8>)>> + 'a>
+    where
+        F: Seek + Read + 'a,
+    {
+        let sections =
+            self.with_input(&mut input, |input| read_index(&self.magic_bytes_opt, input))?;
+
+        Ok(sections.into_iter().map(move |section| {
+            let data = self.with_input(&mut input, |input| {
+                input.seek(SeekFrom::Start(section.offset))?;
+                read_exact_vec(input, section.len)
+            })?;
+
+            Ok((section.name, data))
+        }))
+    }
+}
+
+/// Internal function used to build the index written after the sections by
+/// [`ConfWriterOptions::write_sections`](crate::ConfWriterOptions::write_sections)
+pub(crate) fn section_index(sections: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+    let count = u32::try_from(sections.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many sections"))?;
+    let mut index = count.to_le_bytes().to_vec();
+
+    for (name, data) in sections {
+        let name_len = u16::try_from(name.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("section name {name:?} is too long"),
+            )
+        })?;
+
+        index.extend(name_len.to_le_bytes());
+        index.extend(name.as_bytes());
+        index.extend((data.len() as u64).to_le_bytes());
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{test_util::CountingReader, ConfWriterOptions};
+
+    use super::*;
+
+    /// Writes a fixture with three sections, the middle one being large
+    fn three_sections() -> Cursor<Vec<u8>> {
+        let mut file = Cursor::new(vec![0; 100]);
+        let large = vec![7; 100_000];
+
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .write_sections(
+                &mut file,
+                &[("app", b"port=8080"), ("assets", &large), ("log", b"")],
+            )
+            .unwrap();
+
+        file
+    }
+
+    /// Listing the sections should give exactly the names written, without reading the
+    /// section data
+    #[test]
+    fn lists_sections() {
+        let mut file = CountingReader::new(three_sections());
+
+        let names = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .list_sections(&mut file)
+            .unwrap();
+
+        assert_eq!(names, ["app", "assets", "log"]);
+        assert!(file.bytes_read < 100);
+    }
+
+    /// Each section should read back as written, and missing sections should be reported
+    #[test]
+    fn reads_sections() {
+        let mut file = three_sections();
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(
+            options.read_section(&mut file, "app").unwrap(),
+            b"port=8080"
+        );
+        assert_eq!(
+            options.read_section(&mut file, "assets").unwrap(),
+            [7; 100_000]
+        );
+        assert_eq!(options.read_section(&mut file, "log").unwrap(), b"");
+        assert_eq!(
+            options.read_section(&mut file, "db").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    /// Sections should be read one at a time as the iterator reaches them, so stopping after
+    /// the first never reads the large one after it
+    #[test]
+    fn iterates_sections_lazily() {
+        let mut file = CountingReader::new(three_sections());
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        let mut sections = options.sections_iter(&mut file).unwrap();
+        let (name, data) = sections.next().unwrap().unwrap();
+        drop(sections);
+
+        assert_eq!(
+            (name.as_str(), data.as_slice()),
+            ("app", b"port=8080".as_slice())
+        );
+        assert!(file.bytes_read < 200);
+
+        let all = options
+            .sections_iter(three_sections())
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let names = all
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["app", "assets", "log"]);
+        assert_eq!(all[1].1, [7; 100_000]);
+    }
+
+    /// An index that doesn't lead back to the magic bytes should be rejected
+    #[test]
+    fn rejects_bad_index() {
+        let mut file = three_sections().into_inner();
+        let len_offset = file.len() - 8 - 8;
+        file[len_offset..len_offset + 8].copy_from_slice(&1u64.to_le_bytes());
+
+        assert_eq!(
+            ConfReaderOptions::new(vec![1, 2, 3, 4])
+                .list_sections(&mut Cursor::new(file))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,97 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*};
+
+/// Reader which provides just the bytes of the configuration, for streaming a large payload
+/// into a decoder or another file instead of reading it all into memory first
+///
+/// Created with [`ConfReaderOptions::read_streaming`](crate::ConfReaderOptions::read_streaming)
+#[derive(Debug)]
+pub struct ConfReader<F> {
+    input: io::Take<F>,
+    len: u64,
+}
+
+impl<F> ConfReader<F> {
+    /// Internal constructor used once the input is already seeked to the start of the payload
+    pub(crate) fn new(input: F, len: u64) -> Self
+    where
+        F: Read,
+    {
+        ConfReader {
+            input: input.take(len),
+            len,
+        }
+    }
+
+    /// Length of the whole payload
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the payload is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gives back the wrapped input file
+    pub fn into_inner(self) -> F {
+        self.input.into_inner()
+    }
+}
+
+impl<F: Read> Read for ConfReader<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ConfReaderOptions;
+
+    use super::*;
+
+    /// Streaming the payload to the end should give the same bytes as reading it all at once,
+    /// leaving out the checksum after it
+    #[test]
+    fn streams_same_bytes_as_read() {
+        let mut input_data = vec![0; 1000];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend((0..50_000).map(|i| (i % 200) as u8 + 5));
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let expected = options.read(&mut Cursor::new(&input_data)).unwrap();
+        let mut reader = options.read_streaming(Cursor::new(&input_data)).unwrap();
+        let mut streamed = vec![];
+        reader.read_to_end(&mut streamed).unwrap();
+
+        assert_eq!(reader.len(), 50_000);
+        assert_eq!(streamed, expected);
+
+        options.checksum_present_skip_validation(true);
+        let mut reader = options.read_streaming(Cursor::new(&input_data)).unwrap();
+        let mut streamed = vec![];
+        reader.read_to_end(&mut streamed).unwrap();
+
+        assert_eq!(reader.len(), 50_000 - 4);
+        assert_eq!(streamed, expected[..50_000 - 4]);
+    }
+}
@@ -0,0 +1,157 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    io::{self, prelude::*},
+    mem,
+};
+
+use bytemuck::AnyBitPattern;
+
+use crate::{checksum::ChecksumMode, framing, read_payload, ConfReaderOptions};
+
+impl ConfReaderOptions {
+    /// Reads a payload with a fixed layout straight into a `#[repr(C)]` struct. The payload
+    /// must be exactly `size_of::<T>()` bytes long
+    ///
+    /// Any checksum is validated the same as with [`ConfReaderOptions::read`]. The bytes are
+    /// copied as they are, so the payload must have been written with the same
+    /// endianness and layout as the target the program runs on. Prefer fields with an explicit
+    /// byte order (e.g. storing `[u8; 4]` and converting with `u32::from_le_bytes`) when the
+    /// configuration is produced on a different machine
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// #[derive(Clone, Copy, bytemuck::AnyBitPattern)]
+    /// #[repr(C)]
+    /// struct Conf {
+    ///     port: u16,
+    ///     flags: u16,
+    /// }
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF");
+    /// # buff.extend(8080u16.to_ne_bytes());
+    /// # buff.extend(1u16.to_ne_bytes());
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf: Conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_struct(&mut input)?;
+    /// # assert_eq!(conf.port, 8080);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_struct<T, F>(&self, input: &mut F) -> io::Result<T>
+    where
+        T: AnyBitPattern,
+        F: Seek + Read,
+    {
+        self.with_input(input, |input| {
+            let location = self.locate_once(input)?;
+
+            if location.len != mem::size_of::<T>() as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "payload is {} bytes, but the struct is {} bytes",
+                        location.len,
+                        mem::size_of::<T>()
+                    ),
+                ));
+            }
+
+            let conf = read_payload(input, location)?;
+            let checksum = match self.checksum_opt {
+                ChecksumMode::Validate => Some(framing::read_u32(input)?),
+                _ => None,
+            };
+            self.check_payload(&conf, checksum)?;
+
+            Ok(bytemuck::pod_read_unaligned(&conf))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use bytemuck::AnyBitPattern;
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, AnyBitPattern)]
+    #[repr(C)]
+    struct Conf {
+        magic: [u8; 4],
+        version: u32,
+        limit: u64,
+    }
+
+    /// A payload of the right size should be read as the struct, and any other size rejected
+    #[test]
+    fn reads_repr_c_struct() {
+        let mut input_data = vec![0; 100];
+        input_data.extend(b"CATCONF");
+        input_data.extend(b"conf");
+        input_data.extend(3u32.to_ne_bytes());
+        input_data.extend(1_000_000u64.to_ne_bytes());
+
+        let options = ConfReaderOptions::new(b"CATCONF".to_vec());
+
+        let conf: Conf = options.read_struct(&mut Cursor::new(&input_data)).unwrap();
+        assert_eq!(
+            conf,
+            Conf {
+                magic: *b"conf",
+                version: 3,
+                limit: 1_000_000,
+            }
+        );
+
+        input_data.push(0);
+        let err = options
+            .read_struct::<Conf, _>(&mut Cursor::new(&input_data))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// The checksum after the struct should be validated, and a mismatch rejected
+    #[test]
+    fn validates_struct_checksum() {
+        let mut conf = b"conf".to_vec();
+        conf.extend(3u32.to_ne_bytes());
+        conf.extend(1_000_000u64.to_ne_bytes());
+        let mut input_data = vec![0; 100];
+        input_data.extend(b"CATCONF");
+        input_data.extend(&conf);
+        input_data.extend(crate::checksum::crc32(&conf).to_le_bytes());
+
+        let mut options = ConfReaderOptions::new(b"CATCONF".to_vec());
+        options.checksum(true);
+
+        let read: Conf = options.read_struct(&mut Cursor::new(&input_data)).unwrap();
+        assert_eq!(read.version, 3);
+
+        input_data[110] ^= 0xff;
+        let err = options
+            .read_struct::<Conf, _>(&mut Cursor::new(&input_data))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
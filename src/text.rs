@@ -0,0 +1,158 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{self, prelude::*};
+
+use crate::{CatConfError, ConfReaderOptions};
+
+/// Byte order mark at the start of some UTF-8 text, mostly written by Windows tools
+pub(crate) const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+
+/// Byte order mark at the start of little endian UTF-16 text
+const UTF16_LE_BOM: &[u8] = &[0xff, 0xfe];
+
+/// Byte order mark at the start of big endian UTF-16 text
+const UTF16_BE_BOM: &[u8] = &[0xfe, 0xff];
+
+/// Internal function used to decode UTF-16 text, without the byte order mark, given how to
+/// turn each pair of bytes into a code unit
+fn decode_utf16(text: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, CatConfError> {
+    if !text.len().is_multiple_of(2) {
+        return Err(CatConfError::Utf16);
+    }
+
+    let units = text
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| CatConfError::Utf16)
+}
+
+impl ConfReaderOptions {
+    /// Reads the configuration as text. Payloads starting with a UTF-16 byte order mark, as
+    /// is common for files written on Windows, are converted to UTF-8; anything else has to
+    /// be UTF-8 already. A UTF-8 byte order mark is only removed if
+    /// [`ConfReaderOptions::strip_bom`] is set, and line endings are only changed if
+    /// [`ConfReaderOptions::normalize_newlines`] is set
+    ///
+    /// [`ConfReaderOptions::require_utf8`] should not be set along with this, as it would
+    /// reject UTF-16 payloads before they could be converted
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfReaderOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONF\xff\xfep\0o\0r\0t\0");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec()).read_string(&mut input)?;
+    /// # assert_eq!(conf, "port");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_string<F>(&self, input: &mut F) -> io::Result<String>
+    where
+        F: Seek + Read,
+    {
+        let conf = self.read(input)?;
+
+        let text = if let Some(text) = conf.strip_prefix(UTF16_LE_BOM) {
+            decode_utf16(text, u16::from_le_bytes)?
+        } else if let Some(text) = conf.strip_prefix(UTF16_BE_BOM) {
+            decode_utf16(text, u16::from_be_bytes)?
+        } else {
+            String::from_utf8(conf).map_err(|e| CatConfError::Utf8(e.utf8_error()))?
+        };
+
+        if self.normalize_newlines_opt && text.contains('\r') {
+            return Ok(text.replace("\r\n", "\n").replace('\r', "\n"));
+        }
+
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Appends the payload given after some padding and the magic bytes
+    fn confed(payload: &[u8]) -> Cursor<Vec<u8>> {
+        let mut input_data = vec![0; 64];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(payload);
+        Cursor::new(input_data)
+    }
+
+    /// The UTF-8 byte order mark should only be removed when asked to
+    #[test]
+    fn strips_utf8_bom() {
+        let mut input = confed(b"\xef\xbb\xbfport=8080");
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(options.read(&mut input).unwrap(), b"\xef\xbb\xbfport=8080");
+        assert_eq!(
+            options.read_string(&mut input).unwrap(),
+            "\u{feff}port=8080"
+        );
+
+        options.strip_bom(true);
+        assert_eq!(options.read(&mut input).unwrap(), b"port=8080");
+        assert_eq!(options.read_string(&mut input).unwrap(), "port=8080");
+        assert_eq!(options.read_slice(input.get_ref()).unwrap(), b"port=8080");
+    }
+
+    /// Line endings should only be changed when asked to, including a lone carriage return
+    #[test]
+    fn normalizes_newlines() {
+        let mut input = confed(b"a=1\r\nb=2\rc=3\n");
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(
+            options.read_string(&mut input).unwrap(),
+            "a=1\r\nb=2\rc=3\n"
+        );
+
+        options.normalize_newlines(true);
+        assert_eq!(options.read_string(&mut input).unwrap(), "a=1\nb=2\nc=3\n");
+        assert_eq!(options.read(&mut input).unwrap(), b"a=1\r\nb=2\rc=3\n");
+    }
+
+    /// UTF-16 payloads should be detected by their byte order mark and converted
+    #[test]
+    fn transcodes_utf16() {
+        let options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        let text = "port=8080 ✓";
+
+        let mut le = UTF16_LE_BOM.to_vec();
+        le.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(options.read_string(&mut confed(&le)).unwrap(), text);
+
+        let mut be = UTF16_BE_BOM.to_vec();
+        be.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(options.read_string(&mut confed(&be)).unwrap(), text);
+
+        le.pop();
+        let err = options.read_string(&mut confed(&le)).unwrap_err();
+        assert!(matches!(CatConfError::from(err), CatConfError::Utf16));
+    }
+}
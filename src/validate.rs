@@ -0,0 +1,286 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, prelude::*},
+    path::Path,
+};
+
+use crate::{
+    read_payload,
+    scan::{locate_all_payloads, Scan},
+};
+
+/// Window size used when scanning binaries for validation
+const VALIDATION_WINDOW_SIZE: i64 = 2048;
+
+/// What a binary is expected to look like after configuration has been appended to it, for
+/// use with [`validate_confed_binary`]
+///
+/// The default expects the magic bytes exactly once and a non-empty payload
+pub struct ValidationExpectations {
+    /// How many times the magic bytes should appear in the file, if it matters. Note that if
+    /// the magic bytes are written as a literal in the program, they will appear in the
+    /// original binary as well
+    pub magic_count: Option<usize>,
+    /// Whether an empty payload is acceptable
+    pub allow_empty: bool,
+    /// Minimum length of the payload in bytes
+    pub min_len: Option<u64>,
+    /// Maximum length of the payload in bytes
+    pub max_len: Option<u64>,
+    /// Function used to check the payload against a checksum
+    pub checksum: Option<fn(&[u8]) -> bool>,
+}
+
+impl Default for ValidationExpectations {
+    fn default() -> Self {
+        ValidationExpectations {
+            magic_count: Some(1),
+            allow_empty: false,
+            min_len: None,
+            max_len: None,
+            checksum: None,
+        }
+    }
+}
+
+/// A single problem found by [`validate_confed_binary`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// The magic bytes could not be found at all
+    MagicNotFound,
+    /// The magic bytes were found a different number of times than expected
+    MagicCount {
+        /// How many times the magic bytes were expected
+        expected: usize,
+        /// How many times the magic bytes were found
+        found: usize,
+    },
+    /// The payload was empty
+    EmptyPayload,
+    /// The payload was shorter than the minimum length
+    TooShort {
+        /// Length of the payload
+        len: u64,
+        /// Minimum length expected
+        min: u64,
+    },
+    /// The payload was longer than the maximum length
+    TooLong {
+        /// Length of the payload
+        len: u64,
+        /// Maximum length expected
+        max: u64,
+    },
+    /// The checksum function rejected the payload
+    ChecksumMismatch,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MagicNotFound => write!(f, "magic bytes not found"),
+            ValidationError::MagicCount { expected, found } => write!(
+                f,
+                "expected magic bytes {expected} time(s), found them {found} time(s)"
+            ),
+            ValidationError::EmptyPayload => write!(f, "payload is empty"),
+            ValidationError::TooShort { len, min } => {
+                write!(f, "payload is {len} bytes, expected at least {min}")
+            }
+            ValidationError::TooLong { len, max } => {
+                write!(f, "payload is {len} bytes, expected at most {max}")
+            }
+            ValidationError::ChecksumMismatch => write!(f, "payload checksum does not match"),
+        }
+    }
+}
+
+/// Results of checking a binary with [`validate_confed_binary`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// How many times the magic bytes were found
+    pub magic_count: usize,
+    /// Length of the outermost payload, if there is one
+    pub payload_len: Option<u64>,
+    /// Every problem found with the binary
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    /// Whether the binary met all of the expectations
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Checks a binary with configuration appended to it against a set of expectations, so that a
+/// CI pipeline can refuse to ship a binary without valid configuration
+///
+/// Errors are only returned for problems reading the file; problems with the binary itself are
+/// listed in the report
+///
+/// # Example
+///
+/// ```no_run
+/// use catconf::{validate_confed_binary, ValidationExpectations};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let report = validate_confed_binary(
+///     "target/release/confedbinary",
+///     b"CATCONF",
+///     &ValidationExpectations {
+///         max_len: Some(4096),
+///         ..Default::default()
+///     },
+/// )?;
+///
+/// for error in &report.errors {
+///     eprintln!("{error}");
+/// }
+/// assert!(report.is_ok());
+/// # Ok(())
+/// # }
+/// ```
+pub fn validate_confed_binary<P>(
+    path: P,
+    magic: &[u8],
+    expectations: &ValidationExpectations,
+) -> io::Result<ValidationReport>
+where
+    P: AsRef<Path>,
+{
+    let mut input = OpenOptions::new().read(true).open(path)?;
+    validate(&mut input, magic, expectations)
+}
+
+/// Internal function used to validate any input file
+pub(crate) fn validate<F>(
+    input: &mut F,
+    magic: &[u8],
+    expectations: &ValidationExpectations,
+) -> io::Result<ValidationReport>
+where
+    F: Seek + Read,
+{
+    let locations = locate_all_payloads(&Scan::new(magic, VALIDATION_WINDOW_SIZE), input)?;
+    let mut errors = vec![];
+
+    match expectations.magic_count {
+        _ if locations.is_empty() => errors.push(ValidationError::MagicNotFound),
+        Some(expected) if expected != locations.len() => errors.push(ValidationError::MagicCount {
+            expected,
+            found: locations.len(),
+        }),
+        _ => {}
+    }
+
+    let payload_len = locations.first().map(|location| location.len);
+
+    if let Some(len) = payload_len {
+        if len == 0 && !expectations.allow_empty {
+            errors.push(ValidationError::EmptyPayload);
+        }
+
+        if let Some(min) = expectations.min_len.filter(|min| len < *min) {
+            errors.push(ValidationError::TooShort { len, min });
+        }
+
+        if let Some(max) = expectations.max_len.filter(|max| len > *max) {
+            errors.push(ValidationError::TooLong { len, max });
+        }
+
+        if let Some(checksum) = expectations.checksum {
+            if !checksum(&read_payload(input, locations[0])?) {
+                errors.push(ValidationError::ChecksumMismatch);
+            }
+        }
+    }
+
+    Ok(ValidationReport {
+        magic_count: locations.len(),
+        payload_len,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A binary without any configuration should fail validation
+    #[test]
+    fn flags_missing_config() {
+        let mut buf = Cursor::new(vec![0; 4096]);
+
+        let report = validate(&mut buf, b"CATCONF", &Default::default()).unwrap();
+
+        assert!(!report.is_ok());
+        assert_eq!(report.errors, [ValidationError::MagicNotFound]);
+    }
+
+    /// A well formed binary should pass, and each expectation should be able to fail it
+    #[test]
+    fn passes_well_formed_binary() {
+        let mut input_data = vec![0; 4096];
+        input_data.extend(b"CATCONF");
+        input_data.extend(b"conf");
+        let mut buf = Cursor::new(&input_data);
+
+        let report = validate(
+            &mut buf,
+            b"CATCONF",
+            &ValidationExpectations {
+                min_len: Some(1),
+                max_len: Some(16),
+                checksum: Some(|payload| payload == b"conf"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.payload_len, Some(4));
+
+        let report = validate(
+            &mut buf,
+            b"CATCONF",
+            &ValidationExpectations {
+                magic_count: Some(2),
+                max_len: Some(2),
+                checksum: Some(|_| false),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            report.errors,
+            [
+                ValidationError::MagicCount {
+                    expected: 2,
+                    found: 1
+                },
+                ValidationError::TooLong { len: 4, max: 2 },
+                ValidationError::ChecksumMismatch,
+            ]
+        );
+    }
+}
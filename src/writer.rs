@@ -0,0 +1,829 @@
+// catconf
+// Copyright (C) 2023 Andrew Rioux
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, prelude::*, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    checksum::{crc32, ChecksumMode},
+    content_type::content_type_header,
+    framing::{self, Framing, LengthEncoding},
+    scan::{locate_payload, Scan},
+    sections::section_index,
+    ConfReaderOptions,
+};
+
+/// Window size used to scan for the old configuration when replacing it
+const REPLACE_WINDOW_SIZE: i64 = 2048;
+
+/// Amount of the input copied at a time when replacing configuration
+const REPLACE_CHUNK_SIZE: usize = 8192;
+
+/// Builder struct to allow for configuring how configuration is appended to a file, to be read
+/// back later with a [`ConfReaderOptions`](crate::ConfReaderOptions) using the same options
+///
+/// # Example
+///
+/// ```
+/// use catconf::ConfWriterOptions;
+///
+/// # fn main() -> std::io::Result<()> {
+/// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+/// ConfWriterOptions::new(b"CATCONF".to_vec()).write(&mut file, b"conf")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ConfWriterOptions {
+    magic_bytes_opt: Vec<u8>,
+    framing_opt: Framing,
+    content_type_opt: Option<String>,
+    checksum_opt: bool,
+    length_encoding_opt: LengthEncoding,
+}
+
+impl ConfWriterOptions {
+    /// Create a new ConfWriterOptions builder with the magic bytes specified
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// let options = ConfWriterOptions::new(b"CATCONF".to_vec());
+    /// ```
+    pub fn new(bytes: Vec<u8>) -> Self {
+        ConfWriterOptions {
+            magic_bytes_opt: bytes,
+            framing_opt: Framing::Suffix,
+            content_type_opt: None,
+            checksum_opt: false,
+            length_encoding_opt: LengthEncoding::U64,
+        }
+    }
+
+    /// Set the magic bytes to a different value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// let mut options = ConfWriterOptions::new(b"CATCONF".to_vec());
+    /// options.magic_bytes(b"NOTCATCONF".to_vec());
+    /// ```
+    pub fn magic_bytes(&mut self, bytes: Vec<u8>) -> &mut Self {
+        self.magic_bytes_opt = bytes;
+        self
+    }
+
+    /// Appends the offset of the magic bytes as a little endian `u64` after the configuration,
+    /// to be read with [`ConfReaderOptions::offset_pointer_footer`](crate::ConfReaderOptions::offset_pointer_footer)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .offset_pointer_footer(true)
+    ///     .write(&mut file, b"conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn offset_pointer_footer(&mut self, enabled: bool) -> &mut Self {
+        self.framing_opt = if enabled {
+            Framing::OffsetPointer
+        } else {
+            Framing::Suffix
+        };
+        self
+    }
+
+    /// Appends the length of the configuration as a little endian `u64` after it, to be read
+    /// with [`ConfReaderOptions::magic_and_length`](crate::ConfReaderOptions::magic_and_length)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .write(&mut file, b"conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn magic_and_length(&mut self, enabled: bool) -> &mut Self {
+        self.framing_opt = if enabled {
+            Framing::MagicAndLength
+        } else {
+            Framing::Suffix
+        };
+        self
+    }
+
+    /// Sets how the length written by [`ConfWriterOptions::magic_and_length`] is encoded,
+    /// such as a varint to save space for small configuration
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfWriterOptions, LengthEncoding};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .length_encoding(LengthEncoding::Varint)
+    ///     .write(&mut file, b"conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn length_encoding(&mut self, encoding: LengthEncoding) -> &mut Self {
+        self.length_encoding_opt = encoding;
+        self
+    }
+
+    /// Records a content type such as `json` or `gzip+json` in front of the configuration, so
+    /// that the reader can tell how to parse it with
+    /// [`ConfReaderOptions::read_auto`](crate::ConfReaderOptions::read_auto). The content type
+    /// can be at most 255 bytes long
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .content_type("json")
+    ///     .write(&mut file, br#"{"port": 8080}"#)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_type(&mut self, content_type: &str) -> &mut Self {
+        self.content_type_opt = Some(content_type.to_owned());
+        self
+    }
+
+    /// Appends a little endian CRC-32 of the configuration (including any content type) after
+    /// it, to be checked with [`ConfReaderOptions::checksum`](crate::ConfReaderOptions::checksum)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .checksum(true)
+    ///     .write(&mut file, b"conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checksum(&mut self, enabled: bool) -> &mut Self {
+        self.checksum_opt = enabled;
+        self
+    }
+
+    /// Appends the magic bytes and configuration to the end of the target file
+    ///
+    /// Nothing already in the file is changed, and the magic bytes are written directly after
+    /// it. When reading with plain suffix framing, the magic bytes must therefore not straddle
+    /// the end of the existing content: if the file already ends with the start of the magic
+    /// bytes and the rest of the magic bytes make a copy of them (such as a file ending in
+    /// `CAT` with the magic bytes `CATCAT`), the scan may find the wrong copy. The footer
+    /// framings don't depend on this
+    ///
+    /// With plain suffix framing, configuration which contains the magic bytes is refused
+    /// with an [`io::ErrorKind::InvalidInput`] error, as reading it back would only return
+    /// the part after the last copy; use [`ConfWriterOptions::magic_and_length`] for
+    /// configuration that may contain them
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec()).write(&mut file, b"conf")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write<W>(&self, target: &mut W, conf: &[u8]) -> io::Result<()>
+    where
+        W: Write + Seek,
+    {
+        let magic_offset = target.seek(SeekFrom::End(0))?;
+
+        target.write_all(&self.frame(magic_offset, conf)?)?;
+        target.flush()
+    }
+
+    /// Helper method to go along with [`ConfWriterOptions::write`] in order to append to the
+    /// file at the path given, which is opened in append mode, for build scripts and other
+    /// places where running `cat` through a shell isn't practical
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .write_to_path(Path::new("target/release/app"), b"port=8080")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_to_path(&self, path: &Path, conf: &[u8]) -> io::Result<()> {
+        let mut file = OpenOptions::new().append(true).open(path)?;
+        self.write(&mut file, conf)
+    }
+
+    /// Copies a file whose configuration is followed by a trailer of `trailer_len` bytes, such
+    /// as a signature, to the output with the configuration replaced:
+    ///
+    /// `binary || magic bytes || configuration || footer || trailer`
+    ///
+    /// The old configuration is found using the magic bytes and framing of these options,
+    /// and everything about it is rewritten, including any footer between it and the trailer.
+    /// The original binary and the trailer are copied over byte for byte
+    ///
+    /// The trailer is kept as is rather than updated, so a signature covering the
+    /// configuration will no longer be valid, and has to be applied again to the output
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut signed = vec![0; 4096];
+    /// # signed.extend(b"CATCONFold");
+    /// # signed.extend([0xaa; 64]);
+    /// # let mut input = std::io::Cursor::new(signed);
+    /// let mut output = vec![];
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .replace_config_preserving_trailer(&mut input, &mut output, b"new", 64)?;
+    /// # assert_eq!(&output[4096..4106], b"CATCONFnew");
+    /// # assert_eq!(&output[4106..], [0xaa; 64]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_config_preserving_trailer<R, W>(
+        &self,
+        input: &mut R,
+        output: &mut W,
+        conf: &[u8],
+        trailer_len: u64,
+    ) -> io::Result<()>
+    where
+        R: Read + Seek,
+        W: Write,
+    {
+        let file_len = input.seek(SeekFrom::End(0))?;
+        let trailer_start = file_len.checked_sub(trailer_len).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "trailer is longer than the file",
+            )
+        })?;
+
+        let mut framed = Prefix {
+            inner: &mut *input,
+            len: trailer_start,
+        };
+        let magic_offset = match self.framing_opt {
+            Framing::Suffix => locate_payload(
+                &Scan::new(&self.magic_bytes_opt, REPLACE_WINDOW_SIZE),
+                &mut framed,
+            )?,
+            Framing::OffsetPointer => {
+                framing::locate_offset_pointer(&self.magic_bytes_opt, &mut framed)?
+            }
+            Framing::MagicAndLength => framing::locate_magic_and_length(
+                &self.magic_bytes_opt,
+                self.length_encoding_opt,
+                &mut framed,
+            )?,
+            framing => return Err(framing::unsupported_framing(framing)),
+        }
+        .magic_offset;
+
+        input.seek(SeekFrom::Start(0))?;
+        copy_exact(input, output, magic_offset)?;
+        output.write_all(&self.frame(magic_offset, conf)?)?;
+        input.seek(SeekFrom::Start(trailer_start))?;
+        copy_exact(input, output, trailer_len)?;
+
+        output.flush()
+    }
+
+    /// Internal method used to build everything appended to the binary for the configuration
+    /// given, from the magic bytes to the end of the framing, when the magic bytes are to be
+    /// written at `magic_offset`
+    fn frame(&self, magic_offset: u64, conf: &[u8]) -> io::Result<Vec<u8>> {
+        let mut payload = match &self.content_type_opt {
+            Some(content_type) => content_type_header(content_type)?,
+            None => vec![],
+        };
+        payload.extend(conf);
+
+        if self.checksum_opt {
+            payload.extend(crc32(&payload).to_le_bytes());
+        }
+
+        let mut frame = self.magic_bytes_opt.clone();
+        frame.extend(&payload);
+
+        match self.framing_opt {
+            Framing::Suffix => {
+                let magic_len = self.magic_bytes_opt.len();
+
+                // Another copy anywhere after the start of the magic bytes, including one
+                // overlapping them, could be found instead when reading
+                if magic_len > 0
+                    && frame[1..]
+                        .windows(magic_len)
+                        .any(|window| window == self.magic_bytes_opt)
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "configuration contains the magic bytes, which would cut it short when \
+                         read back with plain suffix framing; use magic_and_length instead",
+                    ));
+                }
+            }
+            Framing::OffsetPointer => frame.extend(magic_offset.to_le_bytes()),
+            Framing::MagicAndLength => {
+                frame.extend(self.length_encoding_opt.encode(payload.len() as u64))
+            }
+            framing => return Err(framing::unsupported_framing(framing)),
+        }
+
+        Ok(frame)
+    }
+
+    /// Appends the magic bytes followed by several named sections of configuration and an
+    /// index of them, to be read back with
+    /// [`ConfReaderOptions::list_sections`](crate::ConfReaderOptions::list_sections) and
+    /// [`ConfReaderOptions::read_section`](crate::ConfReaderOptions::read_section). Section
+    /// names can be at most `u16::MAX` bytes long
+    ///
+    /// The framing, content type, and checksum options don't apply to sections
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::ConfWriterOptions;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut file = std::io::Cursor::new(vec![0; 4096]);
+    /// ConfWriterOptions::new(b"CATCONF".to_vec())
+    ///     .write_sections(&mut file, &[("app", b"port=8080"), ("log", b"level=info")])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_sections<W>(&self, target: &mut W, sections: &[(&str, &[u8])]) -> io::Result<()>
+    where
+        W: Write + Seek,
+    {
+        let index = section_index(sections)?;
+
+        target.seek(SeekFrom::End(0))?;
+        target.write_all(&self.magic_bytes_opt)?;
+
+        for (_, data) in sections {
+            target.write_all(data)?;
+        }
+
+        target.write_all(&index)?;
+        target.write_all(&(index.len() as u64).to_le_bytes())?;
+
+        target.flush()
+    }
+}
+
+impl ConfReaderOptions {
+    /// Copies a file with configuration framed as these options expect to the output, with
+    /// the same configuration framed in the target framing instead, such as for moving binaries
+    /// which used plain suffixes over to [`Framing::MagicAndLength`] without rebuilding them.
+    /// The magic bytes, length encoding, and whether there is a checksum are kept the same,
+    /// and the configuration is copied byte for byte even if these options would change it
+    /// when reading, such as with [`ConfReaderOptions::strip_bom`]
+    ///
+    /// The target can be [`Framing::Suffix`], [`Framing::OffsetPointer`], or
+    /// [`Framing::MagicAndLength`]; any other gives an error before anything is written
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use catconf::{ConfReaderOptions, Framing};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// # let mut buff = vec![0; 4096];
+    /// # buff.extend(b"CATCONFconf");
+    /// # let mut input = std::io::Cursor::new(&buff);
+    /// let mut output = vec![];
+    /// ConfReaderOptions::new(b"CATCONF".to_vec()).reframe(
+    ///     &mut input,
+    ///     &mut output,
+    ///     Framing::MagicAndLength,
+    /// )?;
+    ///
+    /// let conf = ConfReaderOptions::new(b"CATCONF".to_vec())
+    ///     .magic_and_length(true)
+    ///     .read(&mut std::io::Cursor::new(&output))?;
+    /// # assert_eq!(conf, b"conf");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reframe<R, W>(&self, input: &mut R, output: &mut W, target: Framing) -> io::Result<()>
+    where
+        R: Read + Seek,
+        W: Write,
+    {
+        let boundary = self.config_boundary(input)?;
+
+        // The payload is copied as it is stored, without the changes read makes such as
+        // stripping a BOM
+        let (conf, checksum) = self.with_input(input, |input| self.read_once(input))?;
+        self.check_payload(&conf, checksum)?;
+
+        let writer = ConfWriterOptions {
+            framing_opt: target,
+            checksum_opt: self.checksum_opt != ChecksumMode::Absent,
+            length_encoding_opt: self.length_encoding_opt,
+            ..ConfWriterOptions::new(self.magic_bytes_opt.clone())
+        };
+        let frame = writer.frame(boundary, &conf)?;
+
+        input.seek(SeekFrom::Start(0))?;
+        copy_exact(input, output, boundary)?;
+        output.write_all(&frame)?;
+
+        output.flush()
+    }
+}
+
+/// Internal function used to copy exactly `len` bytes from the input to the output
+fn copy_exact<R, W>(input: &mut R, output: &mut W, len: u64) -> io::Result<()>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut chunk = vec![0; REPLACE_CHUNK_SIZE];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let chunk_len = remaining.min(REPLACE_CHUNK_SIZE as u64) as usize;
+        input.read_exact(&mut chunk[..chunk_len])?;
+        output.write_all(&chunk[..chunk_len])?;
+        remaining -= chunk_len as u64;
+    }
+
+    Ok(())
+}
+
+/// View of just the start of a file, so that framings which work from the end of the file can
+/// be used on a file with a trailer after them
+struct Prefix<F> {
+    inner: F,
+    len: u64,
+}
+
+impl<F: Read + Seek> Read for Prefix<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.inner.stream_position()?;
+        let remaining = self.len.saturating_sub(position);
+        let len = remaining.min(buf.len() as u64) as usize;
+
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<F: Seek> Seek for Prefix<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::End(offset) => {
+                let position = self.len.checked_add_signed(offset).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid seek to a negative position",
+                    )
+                })?;
+                self.inner.seek(SeekFrom::Start(position))
+            }
+            pos => self.inner.seek(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Configuration written with an offset pointer footer should read back exactly
+    #[test]
+    fn round_trips_offset_pointer_footer() {
+        let mut file = Cursor::new(vec![0; 100]);
+        let conf = (0..50).collect::<Vec<u8>>();
+
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .offset_pointer_footer(true)
+            .write(&mut file, &conf)
+            .unwrap();
+
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .offset_pointer_footer(true)
+            .read(&mut file)
+            .unwrap();
+
+        assert_eq!(read, conf);
+        assert_eq!(file.get_ref().len(), 100 + 4 + 50 + 8);
+    }
+
+    /// Writing the same configuration to the same binary twice should give byte-identical
+    /// output with every framing and option
+    #[test]
+    fn writes_deterministically() {
+        let binary = (0..300).map(|i| (i % 200) as u8 + 5).collect::<Vec<u8>>();
+        let conf = b"port=8080".as_slice();
+
+        let mut pointer = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        pointer.offset_pointer_footer(true).checksum(true);
+        let mut length = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        length.magic_and_length(true).content_type("text/plain");
+        let mut varint = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+        varint
+            .magic_and_length(true)
+            .length_encoding(LengthEncoding::Varint);
+
+        for writer in [
+            ConfWriterOptions::new(vec![1, 2, 3, 4]),
+            pointer,
+            length,
+            varint,
+        ] {
+            let write = || {
+                let mut file = Cursor::new(binary.clone());
+                writer.write(&mut file, conf).unwrap();
+                file.into_inner()
+            };
+
+            assert_eq!(write(), write());
+        }
+
+        let write_sections = || {
+            let mut file = Cursor::new(binary.clone());
+            ConfWriterOptions::new(vec![1, 2, 3, 4])
+                .write_sections(&mut file, &[("app", conf), ("log", b"")])
+                .unwrap();
+            file.into_inner()
+        };
+        assert_eq!(write_sections(), write_sections());
+    }
+
+    /// Configuration appended to a file on disk should come back exactly through the plain
+    /// reader, with the original contents left alone
+    #[test]
+    fn round_trips_write_to_path() {
+        let path = std::env::temp_dir().join(format!("catconf-write-{}", std::process::id()));
+        let binary = (0..5000).map(|i| (i % 200) as u8 + 5).collect::<Vec<u8>>();
+        let conf = (0..300).map(|i| (i % 7) as u8 + 10).collect::<Vec<u8>>();
+        std::fs::write(&path, &binary).unwrap();
+
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .write_to_path(&path, &conf)
+            .unwrap();
+
+        let mut file = std::fs::File::open(&path).unwrap();
+        let read = crate::read_from_file(&[1, 2, 3, 4], 2048, &mut file).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read, conf);
+        assert_eq!(written[..5000], binary);
+    }
+
+    /// A checksum written with the configuration should validate when read back, along with
+    /// the offset pointer footer after it
+    #[test]
+    fn round_trips_checksum() {
+        let mut file = Cursor::new(vec![0; 100]);
+        let conf = (0..50).collect::<Vec<u8>>();
+
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .checksum(true)
+            .offset_pointer_footer(true)
+            .write(&mut file, &conf)
+            .unwrap();
+
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .checksum(true)
+            .offset_pointer_footer(true)
+            .read(&mut file)
+            .unwrap();
+
+        assert_eq!(read, conf);
+    }
+
+    /// Configuration written with a trailing length should read back exactly, including the
+    /// checksum which is counted as part of the length
+    #[test]
+    fn round_trips_magic_and_length() {
+        let mut file = Cursor::new(vec![0; 100]);
+        let conf = (0..50).collect::<Vec<u8>>();
+
+        ConfWriterOptions::new(vec![1, 2, 3, 4])
+            .magic_and_length(true)
+            .checksum(true)
+            .write(&mut file, &conf)
+            .unwrap();
+
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .magic_and_length(true)
+            .checksum(true)
+            .read(&mut file)
+            .unwrap();
+
+        assert_eq!(read, conf);
+        assert_eq!(file.get_ref()[100 + 4 + 50 + 4..], 54u64.to_le_bytes());
+    }
+
+    /// Configuration containing the magic bytes should be refused with plain suffix framing,
+    /// where reading it back would silently cut it short, but written with a length footer
+    #[test]
+    fn refuses_suffix_config_containing_magic() {
+        let mut options = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+
+        for conf in [&[5, 1, 2, 3, 4, 6][..], &[5, 6, 1, 2, 3][..]] {
+            let mut file = Cursor::new(vec![0; 100]);
+            let conf = [conf, &[4]].concat();
+            let err = options.write(&mut file, &conf).unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(file.get_ref().len(), 100);
+        }
+
+        let mut file = Cursor::new(vec![0; 100]);
+        options
+            .magic_and_length(true)
+            .write(&mut file, &[5, 1, 2, 3, 4, 6])
+            .unwrap();
+    }
+
+    /// Lengths written as varints should read back for lengths taking up one byte, several
+    /// bytes, and right around the boundaries between them
+    #[test]
+    fn round_trips_varint_length() {
+        for len in [0, 1, 127, 128, 300, 16383, 16384, 100_000] {
+            let mut file = Cursor::new(vec![0; 100]);
+            let conf = (0..len).map(|i| (i % 200) as u8 + 5).collect::<Vec<u8>>();
+
+            ConfWriterOptions::new(vec![1, 2, 3, 4])
+                .magic_and_length(true)
+                .length_encoding(LengthEncoding::Varint)
+                .write(&mut file, &conf)
+                .unwrap();
+
+            let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+                .magic_and_length(true)
+                .length_encoding(LengthEncoding::Varint)
+                .read(&mut file)
+                .unwrap();
+
+            assert_eq!(read, conf);
+            assert_eq!(
+                file.get_ref().len(),
+                100 + 4 + len + LengthEncoding::Varint.encode(len as u64).len()
+            );
+        }
+    }
+
+    /// The original binary and the trailer should be kept byte for byte, with only the
+    /// configuration and its footer in between changing
+    #[test]
+    fn replaces_config_preserving_trailer() {
+        let binary = (0..100).map(|i| i % 50 + 10).collect::<Vec<u8>>();
+        let trailer = [0xaa; 32];
+        let mut options = ConfWriterOptions::new(vec![1, 2, 3, 4]);
+
+        for magic_and_length in [false, true] {
+            options.magic_and_length(magic_and_length);
+
+            let mut signed = Cursor::new(binary.clone());
+            options.write(&mut signed, &[5; 16]).unwrap();
+            signed.get_mut().extend(trailer);
+
+            let mut output = vec![];
+            options
+                .replace_config_preserving_trailer(&mut signed, &mut output, &[6; 40], 32)
+                .unwrap();
+
+            assert_eq!(output[..100], binary);
+            assert_eq!(output[output.len() - 32..], trailer);
+
+            output.truncate(output.len() - 32);
+            let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+                .magic_and_length(magic_and_length)
+                .read(&mut Cursor::new(&output))
+                .unwrap();
+            assert_eq!(read, [6; 40]);
+        }
+    }
+
+    /// A plain suffix should be converted to a length footer without touching the binary,
+    /// and be read back with the new framing
+    #[test]
+    fn reframes_suffix_to_length_footer() {
+        let mut input_data = (0..100).map(|i| i % 50 + 10).collect::<Vec<u8>>();
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend([5; 40]);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16);
+
+        let mut output = vec![];
+        options
+            .reframe(
+                &mut Cursor::new(&input_data),
+                &mut output,
+                Framing::MagicAndLength,
+            )
+            .unwrap();
+
+        assert_eq!(output[..144], input_data);
+        assert_eq!(output[144..], 40u64.to_le_bytes());
+
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .magic_and_length(true)
+            .read(&mut Cursor::new(&output))
+            .unwrap();
+        assert_eq!(read, [5; 40]);
+
+        let err = options
+            .reframe(
+                &mut Cursor::new(&input_data),
+                &mut vec![],
+                Framing::Sections,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    /// Reframing should copy the configuration byte for byte, keeping a BOM even when the
+    /// options would strip it when reading
+    #[test]
+    fn reframes_without_stripping_bom() {
+        let mut conf = crate::text::UTF8_BOM.to_vec();
+        conf.extend([5; 40]);
+        let mut input_data = vec![0; 100];
+        input_data.extend([1, 2, 3, 4]);
+        input_data.extend(&conf);
+        let mut options = ConfReaderOptions::new(vec![1, 2, 3, 4]);
+        options.window_size(16).strip_bom(true);
+
+        let mut output = vec![];
+        options
+            .reframe(
+                &mut Cursor::new(&input_data),
+                &mut output,
+                Framing::MagicAndLength,
+            )
+            .unwrap();
+
+        assert_eq!(output[..input_data.len()], input_data);
+        let read = ConfReaderOptions::new(vec![1, 2, 3, 4])
+            .magic_and_length(true)
+            .read(&mut Cursor::new(&output))
+            .unwrap();
+        assert_eq!(read, conf);
+    }
+}